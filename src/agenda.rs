@@ -0,0 +1,241 @@
+use std::collections::HashSet;
+
+use chrono::{Days, NaiveDate, NaiveDateTime};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, BorderType, List, ListState, StatefulWidget},
+};
+use uuid::Uuid;
+
+use crate::{
+    dates,
+    form::form_inputs::enum_field::EnumDisplay,
+    todo::TodoItem,
+    todo_list::{self, SortKey, TodoList},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Section {
+    Overdue,
+    Today,
+    NextWeek,
+    Later,
+    Unscheduled,
+}
+
+impl Section {
+    const ALL: [Section; 5] = [
+        Section::Overdue,
+        Section::Today,
+        Section::NextWeek,
+        Section::Later,
+        Section::Unscheduled,
+    ];
+
+    /// Where an open item belongs, completed items are not on the agenda.
+    fn of(item: &TodoItem, today: NaiveDate, now: NaiveDateTime) -> Section {
+        let next_week = today + Days::new(7);
+        match item.due {
+            _ if item.is_overdue(now) => Section::Overdue,
+            None => Section::Unscheduled,
+            Some(due) if due <= today => Section::Today,
+            Some(due) if due <= next_week => Section::NextWeek,
+            Some(_) => Section::Later,
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            Section::Overdue => "Overdue",
+            Section::Today => "Today",
+            Section::NextWeek => "Next 7 days",
+            Section::Later => "Later",
+            Section::Unscheduled => "Unscheduled",
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            Section::Overdue => Color::LightRed,
+            Section::Today => Color::Yellow,
+            _ => Color::Reset,
+        }
+    }
+}
+
+/// A row of the agenda, what the selection sticks to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Row {
+    Header(Section),
+    Item(Uuid),
+}
+
+/// The open todos bucketed by when they are due, each section with a header
+/// that collapses it.
+///
+/// When an item row is selected it is also the selected item of the [`TodoList`],
+/// so the actions on the selection work on it. A header row leaves the list
+/// without a selection.
+#[derive(Debug, Default)]
+pub struct Agenda {
+    collapsed: HashSet<Section>,
+    selected: Option<Row>,
+    /// Row of the selection, used when the selected row is gone
+    position: usize,
+}
+
+impl Agenda {
+    /// The sections with their open, filtered items, sorted by due date.
+    fn sections<'a>(&self, todos: &'a TodoList) -> Vec<(Section, Vec<&'a TodoItem>)> {
+        let today = dates::today();
+        let now = dates::now();
+        let mut sections: Vec<(Section, Vec<&TodoItem>)> =
+            Section::ALL.iter().map(|&section| (section, Vec::new())).collect();
+        for item in todos.filtered_items().filter(|item| !item.is_completed()) {
+            let section = Section::of(item, today, now);
+            sections
+                .iter_mut()
+                .find(|(other, _)| *other == section)
+                .expect("every section is listed")
+                .1
+                .push(item);
+        }
+        for (_, items) in &mut sections {
+            items.sort_by(|a, b| SortKey::Due.compare(a, b).then_with(|| todo_list::display_order(a, b)));
+        }
+        sections
+    }
+
+    fn rows(&self, todos: &TodoList) -> Vec<Row> {
+        let mut rows = Vec::new();
+        for (section, items) in self.sections(todos) {
+            rows.push(Row::Header(section));
+            if !self.collapsed.contains(&section) {
+                rows.extend(items.iter().map(|item| Row::Item(item.id)));
+            }
+        }
+        rows
+    }
+
+    /// Row of the selection, following the selected row when it moved and
+    /// staying at the same height when it is gone.
+    fn current(&self, rows: &[Row]) -> Option<usize> {
+        let selected = self.selected?;
+        rows.iter()
+            .position(|row| *row == selected)
+            .or_else(|| (!rows.is_empty()).then(|| self.position.min(rows.len() - 1)))
+    }
+
+    fn select(&mut self, todos: &mut TodoList, rows: &[Row], position: Option<usize>) {
+        self.selected = position.and_then(|position| rows.get(position)).copied();
+        self.position = position.unwrap_or_default();
+        match self.selected {
+            Some(Row::Item(id)) => todos.select_id(id),
+            _ => todos.state.select(None),
+        }
+    }
+
+    /// Bring the selection in line with the todos after they changed.
+    pub fn sync(&mut self, todos: &mut TodoList) {
+        let rows = self.rows(todos);
+        let current = self.current(&rows);
+        self.select(todos, &rows, current);
+    }
+
+    /// Select the row `by` rows away, or the first one.
+    pub fn select_row(&mut self, todos: &mut TodoList, by: isize) {
+        let rows = self.rows(todos);
+        let position = match self.current(&rows) {
+            Some(current) => current.saturating_add_signed(by).min(rows.len() - 1),
+            None => 0,
+        };
+        self.select(todos, &rows, Some(position));
+    }
+
+    /// The section of the selected row.
+    fn selected_section(&self, todos: &TodoList) -> Option<Section> {
+        match self.selected? {
+            Row::Header(section) => Some(section),
+            Row::Item(id) => self
+                .sections(todos)
+                .into_iter()
+                .find(|(_, items)| items.iter().any(|item| item.id == id))
+                .map(|(section, _)| section),
+        }
+    }
+
+    pub fn is_header_selected(&self) -> bool {
+        matches!(self.selected, Some(Row::Header(_)))
+    }
+
+    pub fn expand_selected(&mut self, todos: &mut TodoList) {
+        if let Some(section) = self.selected_section(todos) {
+            self.collapsed.remove(&section);
+        }
+    }
+
+    /// Collapse the section of the selected row, the selection moves to its header.
+    pub fn collapse_selected(&mut self, todos: &mut TodoList) {
+        if let Some(section) = self.selected_section(todos) {
+            self.collapsed.insert(section);
+            let rows = self.rows(todos);
+            let position = rows.iter().position(|row| *row == Row::Header(section));
+            self.select(todos, &rows, position);
+        }
+    }
+
+    pub fn toggle_selected(&mut self, todos: &mut TodoList) {
+        match self.selected_section(todos) {
+            Some(section) if self.collapsed.contains(&section) => self.expand_selected(todos),
+            Some(_) => self.collapse_selected(todos),
+            None => {}
+        }
+    }
+
+    pub fn render(&self, todos: &TodoList, area: Rect, buf: &mut Buffer, title: &str) {
+        let now = dates::now();
+        let mut lines = Vec::new();
+        for (section, items) in self.sections(todos) {
+            let collapsed = self.collapsed.contains(&section);
+            let marker = if collapsed { "▸" } else { "▾" };
+            lines.push(Line::styled(
+                format!("{} {} ({})", marker, section.title(), items.len()),
+                Style::new().fg(section.color()).add_modifier(Modifier::BOLD),
+            ));
+            if collapsed {
+                continue;
+            }
+            for item in items {
+                let mut line = Line::from("  ");
+                if !item.priority.is_none() {
+                    line.push_span(Span::styled(format!("[{}] ", item.priority.to_str()), item.priority.style()));
+                }
+                line.push_span(Span::raw(item.title.clone()));
+                if todos.is_blocked(item) {
+                    line.push_span(Span::styled(" blocked", Style::new().fg(Color::LightRed)));
+                }
+                if let Some(due) = item.due {
+                    let color = if item.is_overdue(now) { Color::LightRed } else { Color::Gray };
+                    let due = dates::format_due_short(due, item.due_time);
+                    line.push_span(Span::styled(format!(" ({})", due), Style::new().fg(color)));
+                }
+                lines.push(line);
+            }
+        }
+
+        let mut state = ListState::default();
+        state.select(self.current(&self.rows(todos)));
+        let list = List::new(lines)
+            .block(
+                Block::bordered()
+                    .title(Line::from(title).centered())
+                    .border_type(BorderType::Rounded),
+            )
+            .highlight_symbol(">")
+            .highlight_style(Style::new().bg(Color::Blue));
+        StatefulWidget::render(list, area, buf, &mut state);
+    }
+}
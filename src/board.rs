@@ -0,0 +1,150 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, BorderType, List, ListState, StatefulWidget},
+};
+
+use crate::{
+    dates,
+    form::form_inputs::enum_field::EnumDisplay,
+    todo::{TodoItem, TodoStatus},
+    todo_list::{self, ChangeError, TodoList},
+    workflow::workflow,
+};
+
+/// The Kanban board: one column per status of the workflow, in workflow order.
+///
+/// The selected card is the selected item of the [`TodoList`], so every action on
+/// the selection works on the board too. The board only remembers the column, for
+/// when the selection is empty.
+#[derive(Debug, Default)]
+pub struct Board {
+    column: usize,
+}
+
+impl Board {
+    /// The workflow statuses followed by any other status in use, each with its
+    /// filtered items in list order.
+    fn columns<'a>(&self, todos: &'a TodoList) -> Vec<(TodoStatus, Vec<&'a TodoItem>)> {
+        let mut columns: Vec<(TodoStatus, Vec<&TodoItem>)> = workflow()
+            .statuses()
+            .into_iter()
+            .map(|status| (status, Vec::new()))
+            .collect();
+        for item in todos.filtered_items() {
+            match columns.iter_mut().find(|(status, _)| *status == item.status) {
+                Some((_, items)) => items.push(item),
+                None => columns.push((item.status.clone(), vec![item])),
+            }
+        }
+        for (_, items) in &mut columns {
+            items.sort_by(|a, b| todo_list::display_order(a, b));
+        }
+        columns
+    }
+
+    /// The column and row of the selected card, or the remembered column.
+    fn position(&self, todos: &TodoList) -> (usize, Option<usize>) {
+        let columns = self.columns(todos);
+        if let Some(selected) = todos.get_selected() {
+            for (column, (_, items)) in columns.iter().enumerate() {
+                if let Some(row) = items.iter().position(|item| item.id == selected.id) {
+                    return (column, Some(row));
+                }
+            }
+        }
+        (self.column.min(columns.len() - 1), None)
+    }
+
+    /// Select the card `by` rows away in the current column, or the first one.
+    pub fn select_row(&mut self, todos: &mut TodoList, by: isize) {
+        let (column, row) = self.position(todos);
+        let items = &self.columns(todos)[column].1;
+        let row = match row {
+            Some(row) => row.saturating_add_signed(by).min(items.len().saturating_sub(1)),
+            None => 0,
+        };
+        if let Some(item) = items.get(row) {
+            let id = item.id;
+            todos.select_id(id);
+        }
+    }
+
+    /// Go to the column `by` columns away, selecting the card at the same height.
+    pub fn select_column(&mut self, todos: &mut TodoList, by: isize) {
+        let (column, row) = self.position(todos);
+        let columns = self.columns(todos);
+        self.column = column.saturating_add_signed(by).min(columns.len() - 1);
+        let items = &columns[self.column].1;
+        match items.get(row.unwrap_or_default().min(items.len().saturating_sub(1))) {
+            Some(item) => {
+                let id = item.id;
+                todos.select_id(id);
+            }
+            None => todos.state.select(None),
+        }
+    }
+
+    /// Move the selected card `by` columns, giving it the status of that column.
+    pub fn move_card(&mut self, todos: &mut TodoList, by: isize) -> Result<(), ChangeError> {
+        let (column, row) = self.position(todos);
+        if row.is_none() {
+            return Ok(());
+        }
+        let columns = self.columns(todos);
+        let target = column.saturating_add_signed(by).min(columns.len() - 1);
+        let status = columns[target].0.clone();
+        self.column = target;
+        todos.set_selected_status(|_| status)
+    }
+
+    pub fn render(&self, todos: &TodoList, area: Rect, buf: &mut Buffer) {
+        let columns = self.columns(todos);
+        let (selected_column, selected_row) = self.position(todos);
+        let areas = Layout::horizontal(vec![Constraint::Fill(1); columns.len()]).split(area);
+        let now = dates::now();
+
+        for (column, ((status, items), area)) in columns.iter().zip(areas.iter()).enumerate() {
+            let cards = items.iter().map(|item| {
+                let mut line = Line::default();
+                if !item.priority.is_none() {
+                    line.push_span(Span::styled(format!("[{}] ", item.priority.to_str()), item.priority.style()));
+                }
+                line.push_span(Span::raw(item.title.clone()));
+                if !item.is_completed() && todos.is_blocked(item) {
+                    line.push_span(Span::styled(" blocked", Style::new().fg(Color::LightRed)));
+                }
+                if let Some(due) = item.due {
+                    let color = if item.is_overdue(now) { Color::LightRed } else { Color::Gray };
+                    let due = dates::format_due_short(due, item.due_time);
+                    line.push_span(Span::styled(format!(" ({})", due), Style::new().fg(color)));
+                }
+                line
+            });
+            let title = Line::styled(
+                format!("{} ({})", status.name(), items.len()),
+                Style::new().fg(workflow().color(status)),
+            );
+            let border = match column == selected_column {
+                true => Style::new().fg(Color::Blue),
+                false => Style::new(),
+            };
+            let list = List::new(cards)
+                .block(
+                    Block::bordered()
+                        .title(title.centered())
+                        .border_type(BorderType::Rounded)
+                        .border_style(border),
+                )
+                .highlight_symbol(">")
+                .highlight_style(Style::new().bg(Color::Blue));
+            let mut state = ListState::default();
+            if column == selected_column {
+                state.select(selected_row);
+            }
+            StatefulWidget::render(list, *area, buf, &mut state);
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use chrono::{Datelike, Days, Local, Months, NaiveDate};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{
+        Block, BorderType, List, ListState, Paragraph, StatefulWidget, Widget,
+        calendar::{CalendarEventStore, Monthly},
+    },
+};
+use uuid::Uuid;
+
+use crate::{
+    dates,
+    todo::TodoItem,
+    todo_list::{self, ChangeError, TodoList},
+};
+
+/// A month calendar of due and completed todos, with the todos of the selected
+/// day in a side panel.
+///
+/// As on the board, the selected todo of the day is the selected item of the
+/// [`TodoList`]. A todo is rescheduled by picking it up, going to another day
+/// and dropping it there.
+#[derive(Debug)]
+pub struct Calendar {
+    day: NaiveDate,
+    /// The todo being rescheduled
+    moving: Option<Uuid>,
+}
+
+impl Calendar {
+    pub fn new(day: NaiveDate) -> Self {
+        Self { day, moving: None }
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.moving.is_some()
+    }
+
+    /// The todos due on `day` and the ones completed on it, in list order.
+    fn items_on<'a>(&self, todos: &'a TodoList, day: NaiveDate) -> Vec<&'a TodoItem> {
+        let mut items: Vec<&TodoItem> = todos
+            .filtered_items()
+            .filter(|item| item.due == Some(day) || completed_on(item) == Some(day))
+            .collect();
+        items.sort_by(|a, b| todo_list::display_order(a, b));
+        items
+    }
+
+    /// Go to the day `days` away, selecting its first todo.
+    pub fn move_days(&mut self, todos: &mut TodoList, days: i64) {
+        let day = match days.is_negative() {
+            true => self.day.checked_sub_days(Days::new(days.unsigned_abs())),
+            false => self.day.checked_add_days(Days::new(days.unsigned_abs())),
+        };
+        self.go_to(todos, day.unwrap_or(self.day));
+    }
+
+    /// Go to the same day `months` months away, or the last day of a shorter month.
+    pub fn move_months(&mut self, todos: &mut TodoList, months: i32) {
+        let day = match months.is_negative() {
+            true => self.day.checked_sub_months(Months::new(months.unsigned_abs())),
+            false => self.day.checked_add_months(Months::new(months.unsigned_abs())),
+        };
+        self.go_to(todos, day.unwrap_or(self.day));
+    }
+
+    /// Go to `day`, or the nearest day the calendar can show.
+    pub fn go_to(&mut self, todos: &mut TodoList, day: NaiveDate) {
+        let (first, last) = shown_range();
+        let day = day.clamp(first, last);
+        self.day = day;
+        // The todo being rescheduled stays selected while it is carried around
+        if self.moving.is_some() {
+            return;
+        }
+        match self.items_on(todos, day).first().map(|item| item.id) {
+            Some(id) => todos.select_id(id),
+            None => todos.state.select(None),
+        }
+    }
+
+    /// Select the todo `by` rows away among the todos of the day.
+    pub fn select_row(&mut self, todos: &mut TodoList, by: isize) {
+        let items = self.items_on(todos, self.day);
+        let row = todos
+            .get_selected()
+            .and_then(|selected| items.iter().position(|item| item.id == selected.id));
+        let row = match row {
+            Some(row) => row.saturating_add_signed(by).min(items.len().saturating_sub(1)),
+            None => 0,
+        };
+        if let Some(id) = items.get(row).map(|item| item.id) {
+            todos.select_id(id);
+        }
+    }
+
+    /// Start rescheduling the selected todo when it is one of the day's.
+    pub fn pick_up(&mut self, todos: &TodoList) {
+        let items = self.items_on(todos, self.day);
+        self.moving = todos
+            .get_selected()
+            .filter(|selected| items.iter().any(|item| item.id == selected.id))
+            .map(|selected| selected.id);
+    }
+
+    /// Give the todo being rescheduled the selected day as its due date, keeping its time.
+    pub fn drop(&mut self, todos: &mut TodoList) -> Result<(), ChangeError> {
+        let Some(id) = self.moving.take() else {
+            return Ok(());
+        };
+        let Some(mut item) = todos.items.iter().find(|item| item.id == id).cloned() else {
+            return Ok(());
+        };
+        item.due = Some(self.day);
+        todos.update_item(item)?;
+        todos.select_id(id);
+        Ok(())
+    }
+
+    pub fn cancel(&mut self) {
+        self.moving = None;
+    }
+
+    pub fn render(&self, todos: &TodoList, area: Rect, buf: &mut Buffer) {
+        let [calendar_area, day_area] = Layout::horizontal([Constraint::Length(25), Constraint::Fill(1)]).areas(area);
+        let [month_area, next_month_area, legend_area] = Layout::vertical([
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Fill(1),
+        ])
+        .areas(calendar_area);
+
+        let today = dates::today();
+        let now = dates::now();
+        let mut events = CalendarEventStore::default();
+        for item in todos.filtered_items() {
+            if let Some(day) = completed_on(item).and_then(to_date) {
+                events.add(day, Style::new().fg(Color::Green));
+            }
+        }
+        // Due dates win over completions, overdue ones over everything else
+        let mut due: Vec<&TodoItem> = todos
+            .filtered_items()
+            .filter(|item| item.due.is_some() && !item.is_completed())
+            .collect();
+        due.sort_by_key(|item| item.is_overdue(now));
+        for item in due {
+            let color = if item.is_overdue(now) { Color::LightRed } else { Color::Yellow };
+            if let Some(day) = item.due.and_then(to_date) {
+                events.add(day, Style::new().fg(Color::Black).bg(color));
+            }
+        }
+        for (day, modifier) in [
+            (today, Modifier::UNDERLINED | Modifier::BOLD),
+            (self.day, Modifier::REVERSED),
+        ] {
+            if let Some(day) = to_date(day) {
+                let style = events.0.get(&day).copied().unwrap_or_default();
+                events.add(day, style.add_modifier(modifier));
+            }
+        }
+
+        let first = self.day.with_day(1).expect("every month has a first day");
+        let months = [
+            (Some(first), month_area),
+            (first.checked_add_months(Months::new(1)), next_month_area),
+        ];
+        for (month, area) in months {
+            // The month after the last one the calendar can show stays empty
+            let Some(month) = month.and_then(to_date) else {
+                continue;
+            };
+            Monthly::new(month, &events)
+                .show_month_header(Style::new().add_modifier(Modifier::BOLD))
+                .show_weekdays_header(Style::new().fg(Color::Gray))
+                .show_surrounding(Style::new().fg(Color::DarkGray))
+                .block(Block::bordered().border_type(BorderType::Rounded))
+                .render(area, buf);
+        }
+
+        let legend = vec![
+            Line::from(Span::styled(" overdue ", Style::new().fg(Color::Black).bg(Color::LightRed))),
+            Line::from(Span::styled(" due ", Style::new().fg(Color::Black).bg(Color::Yellow))),
+            Line::from(Span::styled(" completed ", Style::new().fg(Color::Green))),
+            Line::from(Span::styled(" today ", Style::new().add_modifier(Modifier::UNDERLINED | Modifier::BOLD))),
+        ];
+        Paragraph::new(legend).render(legend_area, buf);
+
+        let items = self.items_on(todos, self.day);
+        let moving = self.moving.and_then(|id| todos.items.iter().find(|item| item.id == id));
+        let mut title = format!("{} ({})", self.day.format("%a %d %b %Y"), items.len());
+        if let Some(moving) = moving {
+            title = format!("{} | rescheduling {}", title, moving.title);
+        }
+        let lines = items.iter().map(|item| {
+            let mut line = Line::from(if item.is_completed() { "[✓] " } else { "[✗] " });
+            line.push_span(Span::raw(item.title.clone()));
+            if let Some(time) = item.due_time.filter(|_| item.due == Some(self.day)) {
+                line.push_span(Span::styled(format!(" {}", time.format("%H:%M")), Style::new().fg(Color::Gray)));
+            }
+            if item.due != Some(self.day) {
+                line.push_span(Span::styled(" completed", Style::new().fg(Color::Green)));
+            } else if item.is_overdue(now) {
+                line.push_span(Span::styled(" overdue", Style::new().fg(Color::LightRed)));
+            }
+            line
+        });
+        let mut state = ListState::default();
+        state.select(
+            todos
+                .get_selected()
+                .and_then(|selected| items.iter().position(|item| item.id == selected.id)),
+        );
+        let list = List::new(lines)
+            .block(
+                Block::bordered()
+                    .title(Line::from(title).centered())
+                    .border_type(BorderType::Rounded),
+            )
+            .highlight_symbol(">")
+            .highlight_style(Style::new().bg(Color::Blue));
+        StatefulWidget::render(list, day_area, buf, &mut state);
+    }
+}
+
+/// The local day the item was completed on, if it is completed.
+fn completed_on(item: &TodoItem) -> Option<NaiveDate> {
+    item.completed_at
+        .map(|completed_at| completed_at.with_timezone(&Local).date_naive())
+}
+
+/// The calendar widget works with `time` dates, which only go from year -9999 to 9999.
+fn to_date(day: NaiveDate) -> Option<time::Date> {
+    let month = time::Month::try_from(day.month() as u8).expect("chrono months are 1 to 12");
+    time::Date::from_calendar_date(day.year(), month, day.day() as u8).ok()
+}
+
+/// The first and last day the calendar can go to.
+fn shown_range() -> (NaiveDate, NaiveDate) {
+    let from_date = |date: time::Date| {
+        NaiveDate::from_ymd_opt(date.year(), u8::from(date.month()).into(), date.day().into())
+            .expect("time dates are within the chrono range")
+    };
+    (from_date(time::Date::MIN), from_date(time::Date::MAX))
+}
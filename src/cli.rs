@@ -0,0 +1,239 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::{
+    dates::{self, Due},
+    output::{OutputField, OutputFormat},
+    query::Query,
+    recurrence::Recurrence,
+    storage::{ListLocator, lists},
+    todo::Priority,
+    todo_list::SortKey,
+};
+
+/// A simple terminal todo application.
+///
+/// Without a subcommand the interactive TUI is started.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+    /// Path of the todo data file, overrides the config file
+    #[arg(long, env = "TODO_DATA", value_name = "FILE")]
+    pub data_file: Option<PathBuf>,
+
+    /// Path of the config file
+    #[arg(long, env = "TODO_CONFIG", value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Name of the list to work on, lists other than `default` are created on first use
+    #[arg(long, global = true, env = "TODO_LIST", value_name = "NAME", default_value = ListLocator::DEFAULT, value_parser = parse_list_name)]
+    pub list: String,
+
+    /// Roll the data file back to a backup: `latest`, a position (1 is the newest) or a file name
+    #[arg(long, value_name = "BACKUP", num_args = 0..=1, default_missing_value = "latest")]
+    pub restore_backup: Option<String>,
+
+    /// List the available backups of the data file, newest first
+    #[arg(long)]
+    pub list_backups: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Add a new todo
+    Add {
+        title: String,
+        #[arg(long, default_value = "")]
+        desc: String,
+        /// A status of the workflow, the first open one by default
+        #[arg(long)]
+        status: Option<String>,
+        /// none, low, medium, high or urgent
+        #[arg(long, default_value = "none")]
+        priority: Priority,
+        /// Tags, repeat the option or separate them with commas
+        #[arg(long, value_delimiter = ',')]
+        tag: Vec<String>,
+        /// Add it as a subtask of this todo (an id prefix)
+        #[arg(long, value_name = "ID")]
+        parent: Option<String>,
+        /// Todos that have to be completed first, repeat the option or separate the ids with commas
+        #[arg(long, value_name = "ID", value_delimiter = ',')]
+        depends_on: Vec<String>,
+        /// Due date: today, tomorrow, fri, +3d, 2026-11-01, optionally followed by a time like 14:30
+        #[arg(long, value_parser = parse_due)]
+        due: Option<Due>,
+        /// Repeat rule like FREQ=WEEKLY;BYDAY=MO,TH, completing the todo adds the next occurrence
+        #[arg(long, value_name = "RULE")]
+        repeat: Option<Recurrence>,
+    },
+    /// List the todos
+    List(ListArgs),
+    /// Mark a todo and its subtasks as completed
+    Done {
+        /// Id of the todo, any unique prefix is enough
+        id: String,
+    },
+    /// Reopen a completed todo at the first status of the workflow
+    Reopen {
+        /// Id of the todo, any unique prefix is enough
+        id: String,
+    },
+    /// Remove a todo and its subtasks
+    Rm {
+        /// Id of the todo, any unique prefix is enough
+        id: String,
+    },
+    /// Change the fields of a todo
+    Edit {
+        /// Id of the todo, any unique prefix is enough
+        id: String,
+        #[arg(long)]
+        title: Option<String>,
+        #[arg(long)]
+        desc: Option<String>,
+        #[arg(long)]
+        status: Option<String>,
+        #[arg(long)]
+        priority: Option<Priority>,
+        /// Add tags
+        #[arg(long, value_delimiter = ',')]
+        tag: Vec<String>,
+        /// Remove tags
+        #[arg(long, value_delimiter = ',')]
+        untag: Vec<String>,
+        /// Make it a subtask of this todo (an id prefix)
+        #[arg(long, value_name = "ID", conflicts_with = "no_parent")]
+        parent: Option<String>,
+        /// Move it to the top level
+        #[arg(long)]
+        no_parent: bool,
+        /// Add dependencies on these todos
+        #[arg(long, value_name = "ID", value_delimiter = ',')]
+        depends_on: Vec<String>,
+        /// Remove the dependencies on these todos
+        #[arg(long, value_name = "ID", value_delimiter = ',')]
+        no_depends_on: Vec<String>,
+        /// Due date, same formats as for add
+        #[arg(long, value_parser = parse_due, conflicts_with = "no_due")]
+        due: Option<Due>,
+        /// Remove the due date
+        #[arg(long)]
+        no_due: bool,
+        /// Repeat rule, same format as for add
+        #[arg(long, value_name = "RULE", conflicts_with = "no_repeat")]
+        repeat: Option<Recurrence>,
+        /// Stop repeating
+        #[arg(long)]
+        no_repeat: bool,
+    },
+    /// Summarize what was created and completed recently
+    Report {
+        /// Number of days to look back
+        #[arg(long, default_value_t = 7)]
+        days: u32,
+    },
+    /// Move a todo and its subtasks to another list
+    Move {
+        /// Id of the todo, any unique prefix is enough
+        id: String,
+        /// Name of the list to move it to
+        #[arg(long, value_name = "NAME", value_parser = parse_list_name)]
+        to: String,
+    },
+    /// Show the names of the lists
+    Lists,
+    /// Show every field of a todo
+    Show {
+        /// Id of the todo, any unique prefix is enough
+        id: String,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct OutputArgs {
+    /// Output format, json and ndjson follow the documented output schema
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+
+    /// Comma separated fields to print, in order
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub fields: Option<Vec<OutputField>>,
+}
+
+#[derive(Args, Debug)]
+pub struct ListArgs {
+    /// Only list todos with this status
+    #[arg(long)]
+    pub status: Option<String>,
+
+    /// Only list completed todos
+    #[arg(long, conflicts_with = "open")]
+    pub done: bool,
+
+    /// Only list todos that are not completed
+    #[arg(long)]
+    pub open: bool,
+
+    /// Only list todos with this tag, repeat the option to require several
+    #[arg(long, value_delimiter = ',')]
+    pub tag: Vec<String>,
+
+    /// Only list todos whose title or description contains this text
+    #[arg(long)]
+    pub search: Option<String>,
+
+    /// Only list todos matching this query, like `status:InProgress tag:backend due<+7d -completed "text"`
+    #[arg(long, value_name = "QUERY")]
+    pub filter: Option<Query>,
+
+    /// Sort by this field instead of the list order
+    #[arg(long, value_enum)]
+    pub sort: Option<SortArg>,
+
+    /// Reverse the order
+    #[arg(long)]
+    pub reverse: bool,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+/// The fields `--sort` accepts, see [`SortKey`] for the order of each.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum SortArg {
+    Created,
+    Updated,
+    Completed,
+    Due,
+    Title,
+    Priority,
+}
+
+impl From<SortArg> for SortKey {
+    fn from(sort: SortArg) -> Self {
+        match sort {
+            SortArg::Created => SortKey::Created,
+            SortArg::Updated => SortKey::Updated,
+            SortArg::Completed => SortKey::Completed,
+            SortArg::Due => SortKey::Due,
+            SortArg::Title => SortKey::Title,
+            SortArg::Priority => SortKey::Priority,
+        }
+    }
+}
+
+fn parse_due(value: &str) -> Result<Due, String> {
+    dates::parse_due(value, dates::today())?.ok_or_else(|| "the due date is empty".to_string())
+}
+
+fn parse_list_name(value: &str) -> Result<String, String> {
+    lists::validate_name(value)?;
+    Ok(value.to_string())
+}
@@ -0,0 +1,235 @@
+use std::io::{self, Write};
+
+use chrono::{TimeDelta, Utc};
+use color_eyre::{Report, Result, eyre::eyre};
+
+use crate::{
+    cli::{Command, ListArgs, OutputArgs},
+    dates, output,
+    storage::{ListLocator, TodoQuery},
+    todo::{self, TodoItem, TodoStatus},
+    todo_list::{IdLookupError, SortKey, TodoList},
+};
+
+/// Exit codes of the subcommands, documented in the readme.
+/// Usage errors exit with 2, as reported by clap.
+pub mod exit_code {
+    pub const FAILURE: i32 = 1;
+    pub const NOT_FOUND: i32 = 3;
+    pub const AMBIGUOUS_ID: i32 = 4;
+}
+
+pub fn exit_code(error: &Report) -> i32 {
+    match error.downcast_ref::<IdLookupError>() {
+        Some(IdLookupError::NotFound(_)) => exit_code::NOT_FOUND,
+        Some(IdLookupError::Ambiguous(..)) => exit_code::AMBIGUOUS_ID,
+        None => exit_code::FAILURE,
+    }
+}
+
+/// Run a non-interactive subcommand against the loaded todos. `locator` finds
+/// the other lists for the commands that work across lists.
+pub fn run(command: Command, todos: &mut TodoList, locator: &ListLocator) -> Result<()> {
+    match execute(command, todos, locator, &mut io::stdout().lock()) {
+        // Whoever read the output stopped early, like `todo list | head`, which is not an error
+        Err(err) if is_broken_pipe(&err) => Ok(()),
+        result => result,
+    }
+}
+
+fn is_broken_pipe(error: &Report) -> bool {
+    error
+        .downcast_ref::<io::Error>()
+        .is_some_and(|err| err.kind() == io::ErrorKind::BrokenPipe)
+}
+
+fn execute(command: Command, todos: &mut TodoList, locator: &ListLocator, out: &mut impl Write) -> Result<()> {
+    match command {
+        Command::Add {
+            title,
+            desc,
+            status,
+            priority,
+            tag,
+            parent,
+            depends_on,
+            due,
+            repeat,
+        } => {
+            let status = match status {
+                Some(status) => parse_status(&status)?,
+                None => TodoStatus::default(),
+            };
+            let mut item = TodoItem::new_todo_item(title, desc, status);
+            item.priority = priority;
+            item.tags = todo::parse_tags(&tag.join(","));
+            if let Some(parent) = parent {
+                item.parent_id = Some(todos.find_by_id_prefix(&parent)?.id);
+            }
+            item.depends_on = todos.resolve_ids(&depends_on)?;
+            item.recurrence = repeat.map(|repeat| repeat.to_string());
+            item.set_due(due);
+            let id = item.id;
+            todos.add_item(item)?;
+            writeln!(out, "{}", id)?;
+        }
+        Command::List(args) => list(args, todos, out)?,
+        Command::Done { id } => {
+            let mut item = todos.find_by_id_prefix(&id)?.clone();
+            if !item.is_completed() {
+                item.toggle_completed();
+                todos.update_item(item)?;
+            }
+        }
+        Command::Reopen { id } => {
+            let mut item = todos.find_by_id_prefix(&id)?.clone();
+            if item.is_completed() {
+                item.toggle_completed();
+                todos.update_item(item)?;
+            }
+        }
+        Command::Rm { id } => {
+            let id = todos.find_by_id_prefix(&id)?.id;
+            todos.remove_item(id)?;
+        }
+        Command::Edit {
+            id,
+            title,
+            desc,
+            status,
+            priority,
+            tag,
+            untag,
+            parent,
+            no_parent,
+            depends_on,
+            no_depends_on,
+            due,
+            no_due,
+            repeat,
+            no_repeat,
+        } => {
+            let mut item = todos.find_by_id_prefix(&id)?.clone();
+            if let Some(title) = title {
+                item.title = title;
+            }
+            if let Some(desc) = desc {
+                item.description = desc;
+            }
+            if let Some(status) = status {
+                item.status = parse_status(&status)?;
+            }
+            if let Some(priority) = priority {
+                item.priority = priority;
+            }
+            item.tags.extend(todo::parse_tags(&tag.join(",")));
+            for tag in todo::parse_tags(&untag.join(",")) {
+                item.tags.remove(&tag);
+            }
+            if let Some(parent) = parent {
+                item.parent_id = Some(todos.find_by_id_prefix(&parent)?.id);
+            }
+            if no_parent {
+                item.parent_id = None;
+            }
+            item.depends_on.extend(todos.resolve_ids(&depends_on)?);
+            for id in todos.resolve_ids(&no_depends_on)? {
+                item.depends_on.remove(&id);
+            }
+            if due.is_some() || no_due {
+                item.set_due(due);
+            }
+            if repeat.is_some() || no_repeat {
+                item.recurrence = repeat.map(|repeat| repeat.to_string());
+            }
+            todos.update_item(item)?;
+        }
+        Command::Move { id, to } => {
+            if to == todos.name() {
+                return Err(eyre!("The todo is already in the {} list", to));
+            }
+            let id = todos.find_by_id_prefix(&id)?.id;
+            let mut target = TodoList::new(&to, locator.open(&to));
+            target.read_todos()?;
+            let moved = todos.move_item(id, &mut target)?;
+            let noun = if moved == 1 { "todo" } else { "todos" };
+            writeln!(out, "Moved {} {} to {}", moved, noun, to)?;
+        }
+        Command::Lists => {
+            for name in locator.names()? {
+                let marker = if name == todos.name() { "*" } else { " " };
+                writeln!(out, "{} {}", marker, name)?;
+            }
+        }
+        Command::Report { days } => report(days, todos, out)?,
+        Command::Show { id, output } => {
+            let item = todos.find_by_id_prefix(&id)?;
+            let fields = output.fields.as_deref();
+            output::print_item(out, item, output.format, fields)?;
+        }
+    }
+    Ok(())
+}
+
+fn list(args: ListArgs, todos: &mut TodoList, out: &mut impl Write) -> Result<()> {
+    let completed = match (args.done, args.open) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    };
+    let query = TodoQuery {
+        status: args.status.as_deref().map(parse_status).transpose()?,
+        completed,
+        tags: todo::parse_tags(&args.tag.join(",")).into_iter().collect(),
+        text: args.search,
+    };
+
+    let mut items = todos.query(&query)?;
+    if let Some(filter) = &args.filter {
+        items.retain(|item| filter.matches(item, todos));
+    }
+    if let Some(sort) = args.sort.map(SortKey::from) {
+        items.sort_by(|a, b| sort.compare(a, b));
+    }
+    if args.reverse {
+        items.reverse();
+    }
+    print(out, &items, &args.output)
+}
+
+fn report(days: u32, todos: &TodoList, out: &mut impl Write) -> Result<()> {
+    let since = Utc::now() - TimeDelta::days(days.into());
+    let created = todos.items.iter().filter(|item| item.created_at >= since).count();
+    let mut completed: Vec<&TodoItem> = todos
+        .items
+        .iter()
+        .filter(|item| item.completed_at.is_some_and(|completed_at| completed_at >= since))
+        .collect();
+    completed.sort_by(|a, b| SortKey::Completed.compare(a, b));
+    let now = dates::now();
+    let open = todos.items.iter().filter(|item| !item.is_completed()).count();
+    let overdue = todos.items.iter().filter(|item| item.is_overdue(now)).count();
+
+    writeln!(out, "Last {} days (since {}):", days, dates::format_timestamp(since))?;
+    writeln!(out, "  created:   {}", created)?;
+    writeln!(out, "  completed: {}", completed.len())?;
+    writeln!(out, "Open: {} ({} overdue)", open, overdue)?;
+    if !completed.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "Completed:")?;
+        for item in completed {
+            let completed_at = item.completed_at.map(dates::format_timestamp).unwrap_or_default();
+            writeln!(out, "  {}  {}", completed_at, item.title)?;
+        }
+    }
+    Ok(())
+}
+
+fn print(out: &mut impl Write, items: &[TodoItem], args: &OutputArgs) -> Result<()> {
+    output::print_items(out, items, args.format, args.fields.as_deref())?;
+    Ok(())
+}
+
+fn parse_status(name: &str) -> Result<TodoStatus> {
+    name.parse().map_err(|err: String| eyre!(err))
+}
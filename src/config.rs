@@ -0,0 +1,97 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::{Result, eyre::WrapErr};
+use serde::Deserialize;
+
+use crate::{cli::Cli, storage::StoreBackend};
+
+const APP_DIR: &str = "todo";
+const CONFIG_FILE: &str = "config.toml";
+
+const DEFAULT_BACKUP_COUNT: usize = 5;
+const DEFAULT_UNDO_LIMIT: usize = 100;
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub data_file: Option<PathBuf>,
+    /// `json` (the default) or `sqlite`
+    pub backend: StoreBackend,
+    /// How many timestamped backups of the data file to keep, `0` disables them
+    pub backup_count: usize,
+    /// How many changes the TUI can undo, kept across restarts; `0` disables undo
+    pub undo_limit: usize,
+    /// The workflow, in order; the built-in Pending, InProgress, Completed when empty
+    pub statuses: Vec<StatusConfig>,
+}
+
+/// One `[[statuses]]` entry of the config.
+#[derive(Debug, Deserialize)]
+pub struct StatusConfig {
+    pub name: String,
+    /// A color name like `yellow` or `lightred`, or `#rrggbb`
+    pub color: Option<String>,
+    /// Whether todos in this status count as completed
+    #[serde(default)]
+    pub done: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            data_file: None,
+            backend: StoreBackend::default(),
+            backup_count: DEFAULT_BACKUP_COUNT,
+            undo_limit: DEFAULT_UNDO_LIMIT,
+            statuses: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the config from `path`, or from the platform config directory
+    /// (`$XDG_CONFIG_HOME/todo/config.toml` on Linux) when no path is given.
+    /// A missing config file is not an error, every entry has a default.
+    pub fn load(path: Option<&Path>) -> Result<Config> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match default_config_path() {
+                Some(path) => path,
+                None => return Ok(Config::default()),
+            },
+        };
+
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+
+        let data = fs::read_to_string(&path)
+            .wrap_err_with(|| format!("Unable to read config file {}", path.display()))?;
+        toml::from_str(&data)
+            .wrap_err_with(|| format!("Config file {} is not valid TOML", path.display()))
+    }
+}
+
+/// Resolve where the todos are stored, in order of precedence:
+/// `--data-file`, `TODO_DATA`, the config file and finally
+/// `$XDG_DATA_HOME/todo/data.json` (`data.db` for SQLite, or the platform equivalent).
+pub fn resolve_data_file(cli: &Cli, config: &Config) -> PathBuf {
+    if let Some(path) = &cli.data_file {
+        return path.clone();
+    }
+    if let Some(path) = &config.data_file {
+        return path.clone();
+    }
+    let file_name = config.backend.default_file_name();
+    match dirs::data_dir() {
+        Some(dir) => dir.join(APP_DIR).join(file_name),
+        None => PathBuf::from("data").join(file_name),
+    }
+}
+
+fn default_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_DIR).join(CONFIG_FILE))
+}
@@ -0,0 +1,198 @@
+use chrono::{DateTime, Datelike, Days, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
+
+/// A due date typed by the user, with an optional time of day.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Due {
+    pub date: NaiveDate,
+    pub time: Option<NaiveTime>,
+}
+
+impl Due {
+    pub fn new(date: NaiveDate, time: Option<NaiveTime>) -> Self {
+        Self { date, time }
+    }
+}
+
+pub fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+pub fn now() -> NaiveDateTime {
+    Local::now().naive_local()
+}
+
+/// Parse a due date relative to `today`. An empty input means no due date.
+///
+/// The date is one of `today`, `tomorrow`, `yesterday`, a weekday (`fri`, `friday`,
+/// always the next one after today), an offset (`+3d`, `+2w`, `+1m`), `YYYY-MM-DD`
+/// or `MM-DD` (the next such day). It can be followed by a time, `14:30`, `9am` or `5:15pm`.
+pub fn parse_due(input: &str, today: NaiveDate) -> Result<Option<Due>, String> {
+    let mut words = input.split_whitespace();
+    let Some(date) = words.next() else {
+        return Ok(None);
+    };
+    let date = parse_date(date, today)?;
+    let time = words.next().map(parse_time).transpose()?;
+    if let Some(extra) = words.next() {
+        return Err(format!("unexpected '{}' after the due date", extra));
+    }
+    Ok(Some(Due::new(date, time)))
+}
+
+fn parse_date(input: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    let input = input.to_lowercase();
+    let invalid = || format!("'{}' is not a date", input);
+
+    match input.as_str() {
+        "today" | "tod" => return Ok(today),
+        "tomorrow" | "tom" => return today.checked_add_days(Days::new(1)).ok_or_else(invalid),
+        "yesterday" => return today.checked_sub_days(Days::new(1)).ok_or_else(invalid),
+        _ => {}
+    }
+
+    if let Ok(weekday) = input.parse::<Weekday>() {
+        let ahead = (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+        let ahead = if ahead == 0 { 7 } else { ahead };
+        return today.checked_add_days(Days::new(ahead.into())).ok_or_else(invalid);
+    }
+
+    if let Some(offset) = input.strip_prefix('+') {
+        let unit = offset.chars().last().ok_or_else(invalid)?;
+        let count: u32 = offset[..offset.len() - unit.len_utf8()].parse().map_err(|_| invalid())?;
+        let date = match unit {
+            'd' => today.checked_add_days(Days::new(count.into())),
+            'w' => today.checked_add_days(Days::new(u64::from(count) * 7)),
+            'm' => today.checked_add_months(Months::new(count)),
+            'y' => count.checked_mul(12).and_then(|months| today.checked_add_months(Months::new(months))),
+            _ => None,
+        };
+        return date.ok_or_else(invalid);
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(&input, "%Y-%m-%d") {
+        return Ok(date);
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(&format!("{}-{}", today.year(), input), "%Y-%m-%d") {
+        if date >= today {
+            return Ok(date);
+        }
+        return date.with_year(today.year() + 1).ok_or_else(invalid);
+    }
+
+    Err(invalid())
+}
+
+fn parse_time(input: &str) -> Result<NaiveTime, String> {
+    let input = input.to_lowercase();
+    let invalid = || format!("'{}' is not a time", input);
+
+    if let Ok(time) = NaiveTime::parse_from_str(&input, "%H:%M") {
+        return Ok(time);
+    }
+
+    let (clock, afternoon) = if let Some(clock) = input.strip_suffix("am") {
+        (clock, false)
+    } else if let Some(clock) = input.strip_suffix("pm") {
+        (clock, true)
+    } else {
+        return Err(invalid());
+    };
+    let (hour, minute) = clock.split_once(':').unwrap_or((clock, "0"));
+    let hour: u32 = hour.parse().map_err(|_| invalid())?;
+    let minute: u32 = minute.parse().map_err(|_| invalid())?;
+    if !(1..=12).contains(&hour) {
+        return Err(invalid());
+    }
+    let hour = hour % 12 + if afternoon { 12 } else { 0 };
+    NaiveTime::from_hms_opt(hour, minute, 0).ok_or_else(invalid)
+}
+
+/// `2026-10-23` or `2026-10-23 14:30`, the format accepted back by [`parse_due`].
+pub fn format_due(date: NaiveDate, time: Option<NaiveTime>) -> String {
+    match time {
+        Some(time) => format!("{} {}", date.format("%Y-%m-%d"), time.format("%H:%M")),
+        None => date.format("%Y-%m-%d").to_string(),
+    }
+}
+
+/// Compact form for the list, `Fri 23 Oct` or `Fri 23 Oct 14:30`.
+pub fn format_due_short(date: NaiveDate, time: Option<NaiveTime>) -> String {
+    match time {
+        Some(time) => format!("{} {}", date.format("%a %d %b"), time.format("%H:%M")),
+        None => date.format("%a %d %b").to_string(),
+    }
+}
+
+/// A stored timestamp in local time, `2026-10-18 09:12`.
+pub fn format_timestamp(timestamp: DateTime<Utc>) -> String {
+    timestamp.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn offsets_out_of_range_are_invalid() {
+        let today = date("2026-10-18");
+        assert_eq!(parse_due("+500000000y", today), Err("'+500000000y' is not a date".to_string()));
+        assert!(parse_due("+4294967295m", today).is_err());
+        assert!(parse_due("+4294967295d", today).is_err());
+        assert!(parse_due("tomorrow", NaiveDate::MAX).is_err());
+    }
+
+    /// The date and time `input` stands for on Sunday 2026-10-18.
+    fn due(input: &str) -> (NaiveDate, Option<NaiveTime>) {
+        let due = parse_due(input, date("2026-10-18")).unwrap().unwrap();
+        (due.date, due.time)
+    }
+
+    fn time(text: &str) -> Option<NaiveTime> {
+        Some(NaiveTime::parse_from_str(text, "%H:%M").unwrap())
+    }
+
+    #[test]
+    fn relative_dates() {
+        assert_eq!(parse_due("  ", date("2026-10-18")), Ok(None));
+        assert_eq!(due("today"), (date("2026-10-18"), None));
+        assert_eq!(due("Tom"), (date("2026-10-19"), None));
+        assert_eq!(due("yesterday"), (date("2026-10-17"), None));
+        assert_eq!(due("fri"), (date("2026-10-23"), None));
+        assert_eq!(due("sunday"), (date("2026-10-25"), None));
+        assert_eq!(due("+3d"), (date("2026-10-21"), None));
+        assert_eq!(due("+2w"), (date("2026-11-01"), None));
+        assert_eq!(due("+1m"), (date("2026-11-18"), None));
+        assert_eq!(due("+1y"), (date("2027-10-18"), None));
+        assert_eq!(parse_due("+1m", date("2026-01-31")).unwrap().unwrap().date, date("2026-02-28"));
+    }
+
+    #[test]
+    fn calendar_dates() {
+        assert_eq!(due("2026-12-01"), (date("2026-12-01"), None));
+        assert_eq!(due("10-18"), (date("2026-10-18"), None));
+        assert_eq!(due("12-25"), (date("2026-12-25"), None));
+        assert_eq!(due("10-17"), (date("2027-10-17"), None));
+    }
+
+    #[test]
+    fn times_of_day() {
+        assert_eq!(due("fri 14:30"), (date("2026-10-23"), time("14:30")));
+        assert_eq!(due("tomorrow 9am"), (date("2026-10-19"), time("09:00")));
+        assert_eq!(due("today 5:15PM"), (date("2026-10-18"), time("17:15")));
+        assert_eq!(due("today 12am"), (date("2026-10-18"), time("00:00")));
+        assert_eq!(due("today 12pm"), (date("2026-10-18"), time("12:00")));
+    }
+
+    #[test]
+    fn invalid_input_is_reported() {
+        let today = date("2026-10-18");
+        assert_eq!(parse_due("someday", today), Err("'someday' is not a date".to_string()));
+        assert_eq!(parse_due("+3x", today), Err("'+3x' is not a date".to_string()));
+        assert_eq!(parse_due("today 13pm", today), Err("'13pm' is not a time".to_string()));
+        assert_eq!(parse_due("fri 14:30 sharp", today), Err("unexpected 'sharp' after the due date".to_string()));
+    }
+}
@@ -2,6 +2,8 @@ use ratatui::crossterm::event::KeyCode;
 
 pub mod string_field;
 pub mod enum_field;
+pub mod date_field;
+pub mod tag_field;
 
 pub trait FormInputWidget {
     fn on_key_press(&mut self, key: KeyCode);
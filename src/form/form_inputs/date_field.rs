@@ -0,0 +1,85 @@
+use ratatui::{
+    buffer::Buffer,
+    crossterm::event::KeyCode,
+    layout::{Constraint, Layout, Offset, Rect},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::Widget,
+};
+use serde::Serialize;
+
+use crate::{
+    dates::{self, Due},
+    form::form_inputs::FormInputWidget,
+};
+
+/// Free text date input, see [`dates::parse_due`] for what it accepts.
+/// The date it resolves to is previewed next to the typed text.
+#[derive(Default, Clone, Serialize, Debug)]
+pub struct DateField {
+    label: &'static str,
+    value: String,
+}
+
+impl FormInputWidget for DateField {
+    fn on_key_press(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Char(c) => self.value.push(c),
+            KeyCode::Backspace => {
+                self.value.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl DateField {
+    pub fn new(label: &'static str) -> Self {
+        Self {
+            label,
+            value: String::new(),
+        }
+    }
+
+    pub fn set_value(&mut self, due: Option<Due>) {
+        self.value = match due {
+            Some(due) => dates::format_due(due.date, due.time),
+            None => String::new(),
+        };
+    }
+
+    pub fn get_value(&self) -> Result<Option<Due>, String> {
+        dates::parse_due(&self.value, dates::today())
+    }
+
+    pub fn cursor_offset(&self) -> Offset {
+        let x = (self.label.len() + self.value.chars().count() + 2) as i32;
+        Offset { x, y: 0 }
+    }
+
+    pub fn clear(&mut self) {
+        self.value.clear();
+    }
+}
+
+impl Widget for &DateField {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let [label_area, value_area] = Layout::horizontal([
+            Constraint::Length(self.label.len() as u16 + 2),
+            Constraint::Fill(1),
+        ])
+        .areas(area);
+        let label = Line::from_iter([self.label, ": "]);
+        label.render(label_area, buf);
+
+        let preview = match self.get_value() {
+            Ok(Some(due)) => Span::styled(
+                format!("  -> {}", dates::format_due_short(due.date, due.time)),
+                Style::new().fg(Color::Gray),
+            ),
+            Ok(None) => Span::raw(""),
+            Err(err) => Span::styled(format!("  {}", err), Style::new().fg(Color::LightRed)),
+        };
+        Line::from(vec![Span::raw(self.value.as_str()), preview]).render(value_area, buf);
+    }
+}
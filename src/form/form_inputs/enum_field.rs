@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+
 use ratatui::{
     buffer::Buffer, crossterm::event::KeyCode, layout::{Constraint, Layout, Rect}, text::Line, widgets::Widget
 };
@@ -10,17 +12,18 @@ pub trait EnumDisplay {
     fn to_str(&self) -> &str;
 }
 
-#[derive(Default, Clone, Serialize, Debug, Copy)]
+#[derive(Default, Clone, Serialize, Debug)]
 pub struct EnumField<T>
 where
-    T: EnumDisplay + Copy + PartialEq + 'static,
+    T: EnumDisplay + Clone + PartialEq + 'static,
 {
     label: &'static str,
-    options: &'static [T],
+    /// Fixed options of an enum, or options only known at runtime
+    options: Cow<'static, [T]>,
     selected_index: usize,
 }
 
-impl<T> FormInputWidget for EnumField<T> where T: EnumDisplay + Copy + PartialEq + 'static {
+impl<T> FormInputWidget for EnumField<T> where T: EnumDisplay + Clone + PartialEq + 'static {
     fn on_key_press(&mut self, key: KeyCode) {
         match key {
             KeyCode::Left | KeyCode::Up => {
@@ -40,22 +43,30 @@ impl<T> FormInputWidget for EnumField<T> where T: EnumDisplay + Copy + PartialEq
 
 impl<T> EnumField<T>
 where
-    T: EnumDisplay + Copy + PartialEq + 'static,
+    T: EnumDisplay + Clone + PartialEq + 'static,
 {
     pub fn new(label: &'static str, options: &'static [T]) -> Self {
         Self {
             label,
-            options,
+            options: Cow::Borrowed(options),
+            selected_index: 0,
+        }
+    }
+
+    pub fn with_options(label: &'static str, options: Vec<T>) -> Self {
+        Self {
+            label,
+            options: Cow::Owned(options),
             selected_index: 0,
         }
     }
 
     pub fn get_value(&self) -> T {
-        self.options[self.selected_index]
+        self.options[self.selected_index].clone()
     }
 
     pub fn set_value(&mut self, value: T) {
-        if let Some(pos) = self.options.iter().position(|&v| v == value) {
+        if let Some(pos) = self.options.iter().position(|v| *v == value) {
             self.selected_index = pos;
         }
     }
@@ -64,7 +75,7 @@ where
 
 impl<T> Widget for &EnumField<T>
 where
-    T: EnumDisplay + Copy + PartialEq + 'static,
+    T: EnumDisplay + Clone + PartialEq + 'static,
 {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let [label_area, value_area] = Layout::horizontal([
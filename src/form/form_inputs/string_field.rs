@@ -13,11 +13,15 @@ use crate::form::form_inputs::FormInputWidget;
 pub struct StringField {
     label: &'static str,
     value: String,
+    read_only: bool,
 }
 
 impl FormInputWidget for StringField {
     /// Handle input events for the string input.
     fn on_key_press(&mut self, key: KeyCode) {
+        if self.read_only {
+            return;
+        }
         match key {
             KeyCode::Char(c) => self.value.push(c),
             KeyCode::Backspace => {
@@ -33,6 +37,7 @@ impl StringField {
         Self {
             label,
             value: String::new(),
+            read_only: false,
         }
     }
 
@@ -40,9 +45,15 @@ impl StringField {
         Self {
             label: self.label,
             value,
+            read_only: self.read_only,
         }
     }
 
+    /// A read-only field is still rendered but ignores all key presses.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
     pub fn set_value(&mut self, value: String) {
         self.value = value;
     }
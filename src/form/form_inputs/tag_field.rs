@@ -0,0 +1,107 @@
+use std::collections::BTreeSet;
+
+use ratatui::{
+    buffer::Buffer,
+    crossterm::event::KeyCode,
+    layout::{Constraint, Layout, Offset, Rect},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::Widget,
+};
+use serde::Serialize;
+
+use crate::{form::form_inputs::FormInputWidget, todo};
+
+/// Comma or space separated tags. While a tag is being typed the first known tag
+/// that starts with it is suggested, Right accepts the suggestion.
+#[derive(Default, Clone, Serialize, Debug)]
+pub struct TagField {
+    label: &'static str,
+    value: String,
+    /// Tags already in use, offered as completions
+    #[serde(skip)]
+    known: Vec<String>,
+}
+
+impl FormInputWidget for TagField {
+    fn on_key_press(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Char(c) => self.value.push(c),
+            KeyCode::Backspace => {
+                self.value.pop();
+            }
+            KeyCode::Right => {
+                if let Some(rest) = self.suggestion() {
+                    let rest = rest.to_string();
+                    self.value.push_str(&rest);
+                    self.value.push_str(", ");
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl TagField {
+    pub fn new(label: &'static str) -> Self {
+        Self {
+            label,
+            value: String::new(),
+            known: Vec::new(),
+        }
+    }
+
+    pub fn set_known_tags(&mut self, known: Vec<String>) {
+        self.known = known;
+    }
+
+    pub fn set_value(&mut self, tags: &BTreeSet<String>) {
+        self.value = tags.iter().cloned().collect::<Vec<_>>().join(", ");
+    }
+
+    pub fn get_value(&self) -> BTreeSet<String> {
+        todo::parse_tags(&self.value)
+    }
+
+    /// The rest of the known tag that completes the tag being typed.
+    fn suggestion(&self) -> Option<&str> {
+        let partial = self.value.rsplit([',', ' ']).next().unwrap_or_default();
+        let partial = partial.trim_start_matches('#').to_lowercase();
+        if partial.is_empty() {
+            return None;
+        }
+        let entered = self.get_value();
+        self.known
+            .iter()
+            .find(|tag| tag.starts_with(&partial) && tag.len() > partial.len() && !entered.contains(*tag))
+            .map(|tag| &tag[partial.len()..])
+    }
+
+    pub fn cursor_offset(&self) -> Offset {
+        let x = (self.label.len() + self.value.chars().count() + 2) as i32;
+        Offset { x, y: 0 }
+    }
+
+    pub fn clear(&mut self) {
+        self.value.clear();
+    }
+}
+
+impl Widget for &TagField {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let [label_area, value_area] = Layout::horizontal([
+            Constraint::Length(self.label.len() as u16 + 2),
+            Constraint::Fill(1),
+        ])
+        .areas(area);
+        let label = Line::from_iter([self.label, ": "]);
+        label.render(label_area, buf);
+
+        let mut line = Line::from(self.value.as_str());
+        if let Some(rest) = self.suggestion() {
+            line.push_span(Span::styled(rest, Style::new().fg(Color::DarkGray)));
+            line.push_span(Span::styled("  (Right to complete)", Style::new().fg(Color::Gray)));
+        }
+        line.render(value_area, buf);
+    }
+}
@@ -16,37 +16,28 @@ impl FormStatus {
 
     pub fn handle_key_press(&mut self, key: KeyCode, key_event: KeyEvent) -> Option<KeyCode> {
         if key_event.modifiers.contains(KeyModifiers::CONTROL) {
-            match key {
-                KeyCode::Char('s') => {
-                    *self = FormStatus::Submitting;
-                    return None;
-                }
-                _ => {}
+            if let KeyCode::Char('s') = key {
+                *self = FormStatus::Submitting;
             }
             return None;
         }
         match self {
             FormStatus::Viewing => {
-                match key {
-                    KeyCode::Enter => {
-                        *self = FormStatus::Editing;
-                        return None;
-                    }
-                    _ => {return None;}
+                if key == KeyCode::Enter {
+                    *self = FormStatus::Editing;
                 }
+                None
             },
             FormStatus::Editing => {
                 match key {
                     KeyCode::Enter => {
                         *self = FormStatus::Viewing;
-                        return None;
+                        None
                     }
-                    _ => {return Some(key);}
+                    _ => Some(key),
                 }
             },
-            FormStatus::Submitting => {
-                return None;
-            },
+            FormStatus::Submitting => None,
         }
     }
 
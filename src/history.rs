@@ -0,0 +1,279 @@
+use std::{fs, io, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    storage::{StorageError, write_atomic},
+    todo::TodoItem,
+};
+
+/// One item before and after a change, `None` when it did not exist.
+/// At least one side is always there, a saved change without either is rejected when it is read.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "SavedChange")]
+pub struct ItemChange {
+    pub before: Option<TodoItem>,
+    pub after: Option<TodoItem>,
+}
+
+/// An [`ItemChange`] as read from the history file, before it is checked.
+#[derive(Deserialize)]
+struct SavedChange {
+    before: Option<TodoItem>,
+    after: Option<TodoItem>,
+}
+
+impl TryFrom<SavedChange> for ItemChange {
+    type Error = &'static str;
+
+    fn try_from(saved: SavedChange) -> Result<Self, Self::Error> {
+        match (&saved.before, &saved.after) {
+            (None, None) => Err("a change needs an item before or after it"),
+            _ => Ok(ItemChange {
+                before: saved.before,
+                after: saved.after,
+            }),
+        }
+    }
+}
+
+impl ItemChange {
+    pub fn id(&self) -> Uuid {
+        self.before
+            .as_ref()
+            .or(self.after.as_ref())
+            .map(|item| item.id)
+            .expect("a change has an item on at least one side")
+    }
+
+    fn title(&self) -> &str {
+        self.after.as_ref().or(self.before.as_ref()).map_or("", |item| &item.title)
+    }
+
+    /// The change going the other way.
+    fn reversed(&self) -> ItemChange {
+        ItemChange {
+            before: self.after.clone(),
+            after: self.before.clone(),
+        }
+    }
+}
+
+/// The changes one action made to one list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListChanges {
+    pub list: String,
+    pub changes: Vec<ItemChange>,
+}
+
+/// Everything a single action changed, across all lists.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Operation {
+    pub lists: Vec<ListChanges>,
+}
+
+impl Operation {
+    /// The operation that undoes this one.
+    pub fn reversed(&self) -> Operation {
+        let lists = self.lists.iter().rev().map(|list| ListChanges {
+            list: list.list.clone(),
+            changes: list.changes.iter().map(ItemChange::reversed).collect(),
+        });
+        Operation { lists: lists.collect() }
+    }
+
+    /// What the action did, like `removed Buy milk` or `changed 3 todos`.
+    pub fn describe(&self) -> String {
+        let changes = || self.lists.iter().flat_map(|list| &list.changes);
+        let mut ids: Vec<Uuid> = changes().map(ItemChange::id).collect();
+        ids.sort();
+        ids.dedup();
+        let added = changes().filter(|change| change.before.is_none()).count();
+        let removed = changes().filter(|change| change.after.is_none()).count();
+        let total = changes().count();
+
+        let verb = if added > 0 && added == removed && added * 2 == total {
+            "moved"
+        } else if added == total {
+            "added"
+        } else if removed == total {
+            "removed"
+        } else {
+            "changed"
+        };
+        match ids.len() {
+            1 => format!("{} {}", verb, changes().next().map_or("", ItemChange::title)),
+            count => format!("{} {} todos", verb, count),
+        }
+    }
+}
+
+/// The actions that can be undone and redone, newest last, kept across restarts
+/// in a file next to the data file.
+#[derive(Debug)]
+pub struct History {
+    path: PathBuf,
+    /// How many actions are kept, `0` disables the history
+    limit: usize,
+    log: HistoryLog,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HistoryLog {
+    undo: Vec<Operation>,
+    redo: Vec<Operation>,
+}
+
+impl History {
+    pub fn new(path: PathBuf, limit: usize) -> Self {
+        Self {
+            path,
+            limit,
+            log: HistoryLog::default(),
+        }
+    }
+
+    /// Read the history saved by an earlier session, a missing file is an empty history.
+    pub fn load(path: PathBuf, limit: usize) -> Result<History, StorageError> {
+        let mut history = History::new(path, limit);
+        if limit == 0 {
+            return Ok(history);
+        }
+        let data = match fs::read_to_string(&history.path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(history),
+            Err(err) => return Err(StorageError::io(&history.path, err)),
+        };
+        history.log = serde_json::from_str(&data).map_err(|source| StorageError::Malformed {
+            path: history.path.clone(),
+            source,
+        })?;
+        history.trim();
+        Ok(history)
+    }
+
+    /// Remember a new action, which makes the undone ones impossible to redo.
+    pub fn record(&mut self, operation: Operation) {
+        self.log.redo.clear();
+        self.log.undo.push(operation);
+        self.trim();
+    }
+
+    /// The action to undo next, hand it back with [`History::undone`] once it is undone.
+    pub fn take_undo(&mut self) -> Option<Operation> {
+        self.log.undo.pop()
+    }
+
+    pub fn undone(&mut self, operation: Operation) {
+        self.log.redo.push(operation);
+    }
+
+    /// The action to redo next, hand it back with [`History::redone`] once it is redone.
+    pub fn take_redo(&mut self) -> Option<Operation> {
+        self.log.redo.pop()
+    }
+
+    pub fn redone(&mut self, operation: Operation) {
+        self.log.undo.push(operation);
+        self.trim();
+    }
+
+    /// Write the history to its file, nothing is written while it is disabled.
+    pub fn save(&self) -> Result<(), StorageError> {
+        if self.limit == 0 {
+            return Ok(());
+        }
+        let data = serde_json::to_string(&self.log).map_err(StorageError::Serialize)?;
+        write_atomic(&self.path, data.as_bytes())
+    }
+
+    /// Drop the oldest actions beyond the limit.
+    fn trim(&mut self) {
+        for stack in [&mut self.log.undo, &mut self.log.redo] {
+            let excess = stack.len().saturating_sub(self.limit);
+            stack.drain(..excess);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::todo::TodoStatus;
+
+    fn path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("todo-history-{}-{}.json", name, std::process::id()))
+    }
+
+    /// An operation that adds a todo with this title to the `inbox` list.
+    fn added(title: &str) -> Operation {
+        let item = TodoItem::new_todo_item(title.to_string(), String::new(), TodoStatus::default());
+        Operation {
+            lists: vec![ListChanges {
+                list: "inbox".to_string(),
+                changes: vec![ItemChange {
+                    before: None,
+                    after: Some(item),
+                }],
+            }],
+        }
+    }
+
+    fn describe(operation: Option<Operation>) -> Option<String> {
+        operation.as_ref().map(Operation::describe)
+    }
+
+    #[test]
+    fn undone_actions_can_be_redone_until_a_new_one_is_recorded() {
+        let mut history = History::new(path("redo"), 10);
+        history.record(added("a"));
+        history.record(added("b"));
+
+        let undo = history.take_undo().unwrap();
+        assert_eq!(undo.describe(), "added b");
+        history.undone(undo.reversed());
+        let redo = history.take_redo().unwrap();
+        assert_eq!(redo.describe(), "removed b");
+        history.redone(redo.reversed());
+        assert_eq!(describe(history.take_undo()), Some("added b".to_string()));
+
+        history.undone(added("b").reversed());
+        history.record(added("c"));
+        assert!(history.take_redo().is_none());
+        assert_eq!(describe(history.take_undo()), Some("added c".to_string()));
+        assert_eq!(describe(history.take_undo()), Some("added a".to_string()));
+    }
+
+    #[test]
+    fn only_the_newest_actions_are_kept() {
+        let mut history = History::new(path("trim"), 2);
+        for title in ["a", "b", "c"] {
+            history.record(added(title));
+        }
+        assert_eq!(describe(history.take_undo()), Some("added c".to_string()));
+        assert_eq!(describe(history.take_undo()), Some("added b".to_string()));
+        assert!(history.take_undo().is_none());
+    }
+
+    #[test]
+    fn the_history_survives_a_restart() {
+        let path = path("restart");
+        let mut history = History::new(path.clone(), 10);
+        history.record(added("a"));
+        history.save().unwrap();
+
+        let mut loaded = History::load(path.clone(), 10).unwrap();
+        assert_eq!(describe(loaded.take_undo()), Some("added a".to_string()));
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn a_change_without_an_item_is_rejected() {
+        let path = path("empty-change");
+        let log = r#"{"undo": [{"lists": [{"list": "inbox", "changes": [{"before": null, "after": null}]}]}], "redo": []}"#;
+        fs::write(&path, log).unwrap();
+        assert!(matches!(History::load(path.clone(), 10), Err(StorageError::Malformed { .. })));
+        let _ = fs::remove_file(path);
+    }
+}
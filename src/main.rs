@@ -1,35 +1,319 @@
-use color_eyre::Result;
+use color_eyre::{Result, eyre::eyre};
 use ratatui::{
-    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers}, layout::{Constraint, Layout}, style::{Color, Style}, text::ToSpan, widgets::{Block, BorderType, List, Paragraph, StatefulWidget, Widget}, DefaultTerminal, Frame
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers}, layout::{Constraint, Layout, Rect}, style::{Color, Style}, text::{Line, Text, ToSpan}, widgets::{Block, BorderType, List, Paragraph, StatefulWidget, Tabs, Widget, Wrap}, DefaultTerminal, Frame
 };
 
+mod agenda;
+mod board;
+mod calendar;
+mod cli;
+mod commands;
+mod config;
+mod dates;
 mod form;
+mod history;
+mod output;
+mod query;
+mod recurrence;
+mod storage;
 mod todo;
 mod todo_form;
 mod todo_list;
+mod workflow;
 
-use crate::{todo_form::TodoForm, todo_list::TodoList};
+use clap::Parser;
+
+use crate::{agenda::Agenda, board::Board, calendar::Calendar, cli::Cli, config::Config, history::{History, ListChanges, Operation}, query::Query, storage::{ListLocator, StorageError}, todo_form::TodoForm, todo_list::{BulkSummary, ChangeError, TodoList}, workflow::Workflow};
 
 #[derive(Debug)]
 enum AppMode {
     Normal = 0,
     Editing,
     Adding,
+    Recovery,
+    /// Picking the list to move the selected item to
+    Moving,
+    /// Typing the search in the Help area, the list is filtered as it is typed
+    Searching,
+    /// Typing a filter query in the Help area, applied with Enter
+    Filtering,
+    /// Picking the status for the marked items
+    SettingStatus,
+    /// Typing the tags to add to and remove from the marked items
+    Tagging,
+}
+
+/// What the main area shows, `v` goes to the next one.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum View {
+    #[default]
+    Agenda,
+    List,
+    Board,
+    Calendar,
+}
+
+impl View {
+    fn next(self) -> View {
+        match self {
+            View::Agenda => View::List,
+            View::List => View::Board,
+            View::Board => View::Calendar,
+            View::Calendar => View::Agenda,
+        }
+    }
 }
 
 #[derive(Debug)]
 struct AppState {
-    todos: TodoList,
+    /// Every list, shown as tabs
+    lists: Vec<TodoList>,
+    /// Index of the list on screen
+    current: usize,
     form: TodoForm,
     mode: AppMode,
+    view: View,
+    agenda: Agenda,
+    board: Board,
+    calendar: Calendar,
+    /// What `u` undoes and Ctrl+r redoes
+    history: History,
+    /// Last failed change, shown in the Help area until the next successful one
+    error: Option<String>,
+    /// Outcome of the last bulk action, shown in the Help area until the next key
+    message: Option<String>,
+    /// Set when quitting failed to save, a second quit right after discards the unsaved changes
+    quit_pending: bool,
+    /// The filter query being typed
+    filter_input: String,
+    /// The tag changes being typed, like `+backend -urgent`
+    tag_input: String,
 }
 
-impl Default for AppState {
-    fn default() -> Self {
+impl AppState {
+    fn new(lists: Vec<TodoList>, current: usize, history: History) -> Self {
         Self {
-            todos: TodoList::default(),
+            lists,
+            current,
             mode: AppMode::Normal,
+            view: View::default(),
+            agenda: Agenda::default(),
+            board: Board::default(),
+            calendar: Calendar::new(dates::today()),
+            history,
             form: TodoForm::default(),
+            error: None,
+            message: None,
+            quit_pending: false,
+            filter_input: String::new(),
+            tag_input: String::new(),
+        }
+    }
+
+    /// The list on screen.
+    fn todos(&self) -> &TodoList {
+        &self.lists[self.current]
+    }
+
+    fn todos_mut(&mut self) -> &mut TodoList {
+        &mut self.lists[self.current]
+    }
+
+    /// Open the form on the selected item.
+    fn edit_selected(&mut self) {
+        if let Some(item) = self.lists[self.current].get_selected() {
+            self.form.load_item(item, &self.lists[self.current]);
+            self.form.tags.set_known_tags(self.todos().tags());
+            self.mode = AppMode::Editing;
+        }
+    }
+
+    /// Show the list at `index`, reading it the first time it is shown.
+    fn switch_list(&mut self, index: usize) {
+        let previous = self.current;
+        self.current = index;
+        if self.todos().is_loaded() {
+            return;
+        }
+        if let Err(err) = self.todos_mut().read_todos() {
+            self.error = Some(err.to_string());
+            if err.is_malformed() {
+                self.mode = AppMode::Recovery;
+            } else {
+                self.current = previous;
+            }
+        }
+    }
+
+    /// Move the marked or selected items and their subtasks to the list at `target`.
+    fn move_selected(&mut self, target: usize) {
+        let ids = self.todos().targets();
+        if !self.lists[target].is_loaded()
+            && let Err(err) = self.lists[target].read_todos()
+        {
+            self.error = Some(format!("Could not open the {} list: {}", self.lists[target].name(), err));
+            return;
+        }
+        let [source, target] = self
+            .lists
+            .get_disjoint_mut([self.current, target])
+            .expect("the target is another list");
+        let name = target.name().to_string();
+        let result = source.move_many(&ids, target);
+        self.summarize(&format!("Moved to {}:", name), result);
+    }
+
+    /// Record what the last action changed, in any list, as one step of the undo history.
+    fn record_changes(&mut self) {
+        let lists: Vec<ListChanges> = self
+            .lists
+            .iter_mut()
+            .filter_map(|todos| {
+                let changes = todos.take_changes();
+                let list = todos.name().to_string();
+                (!changes.is_empty()).then_some(ListChanges { list, changes })
+            })
+            .collect();
+        if lists.is_empty() {
+            return;
+        }
+        self.history.record(Operation { lists });
+        self.save_history();
+    }
+
+    /// Keep the agenda selection on a row that still exists after the todos changed.
+    fn sync_agenda(&mut self) {
+        if matches!(self.view, View::Agenda) {
+            self.agenda.sync(&mut self.lists[self.current]);
+        }
+    }
+
+    fn save_history(&mut self) {
+        if let Err(err) = self.history.save() {
+            self.error = Some(format!("Could not save the undo history: {}", err));
+        }
+    }
+
+    /// Undo the last action, or redo the last undone one with `redo`. An action is
+    /// dropped from the history when its todos were changed since, e.g. from the
+    /// command line, as undoing it would lose those changes.
+    fn undo(&mut self, redo: bool) {
+        let (verb, operation) = match redo {
+            true => ("redo", self.history.take_redo()),
+            false => ("undo", self.history.take_undo()),
+        };
+        let Some(operation) = operation else {
+            self.message = Some(format!("Nothing to {}", verb));
+            return;
+        };
+        let changes = if redo { operation.clone() } else { operation.reversed() };
+        if let Err(err) = self.load_lists(&changes) {
+            self.error = Some(err.to_string());
+            // Nothing was changed, the action stays where it was
+            match redo {
+                true => self.history.undone(operation),
+                false => self.history.redone(operation),
+            }
+            return;
+        }
+        match self.apply(&changes) {
+            Ok(true) => {
+                self.error = None;
+                self.message = Some(format!("{}: {}", if redo { "Redone" } else { "Undone" }, operation.describe()));
+            }
+            Ok(false) => {
+                self.error = Some(format!("Cannot {} \"{}\", the todos were changed since", verb, operation.describe()));
+                self.save_history();
+                return;
+            }
+            Err(err) => self.error = Some(err.to_string()),
+        }
+        match redo {
+            true => self.history.redone(operation),
+            false => self.history.undone(operation),
+        }
+        self.save_history();
+    }
+
+    /// Read the lists the changes are for, unless they already are.
+    fn load_lists(&mut self, operation: &Operation) -> Result<(), StorageError> {
+        for changes in &operation.lists {
+            if let Some(todos) = self.lists.iter_mut().find(|todos| todos.name() == changes.list)
+                && !todos.is_loaded()
+            {
+                todos.read_todos()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply the changes of an undo or redo to their lists, showing the first of them.
+    /// Returns `false` without changing anything when a list is gone or a todo is no
+    /// longer as the changes expect it.
+    fn apply(&mut self, operation: &Operation) -> Result<bool, StorageError> {
+        let mut indexes = Vec::new();
+        for changes in &operation.lists {
+            let Some(index) = self.lists.iter().position(|todos| todos.name() == changes.list) else {
+                return Ok(false);
+            };
+            if !self.lists[index].is_unchanged_since(&changes.changes) {
+                return Ok(false);
+            }
+            indexes.push(index);
+        }
+        if let Some(&first) = indexes.first()
+            && !indexes.contains(&self.current)
+        {
+            self.current = first;
+        }
+        for (changes, index) in operation.lists.iter().zip(indexes) {
+            self.lists[index].apply(&changes.changes)?;
+        }
+        Ok(true)
+    }
+
+    /// Show how a bulk action went in the Help area.
+    fn summarize(&mut self, action: &str, result: Result<BulkSummary, StorageError>) {
+        let summary = match result {
+            Ok(summary) => summary,
+            Err(err) => {
+                self.report(Err::<(), _>(err));
+                return;
+            }
+        };
+        self.error = None;
+        let todos = if summary.changed == 1 { "todo" } else { "todos" };
+        let mut message = format!("{} {} {}", action, summary.changed, todos);
+        if !summary.skipped.is_empty() {
+            message.push_str(&format!(", skipped {}: {}", summary.skipped.len(), summary.skipped.join("; ")));
+        }
+        self.message = Some(message);
+    }
+
+    /// Save the todos of every list, keeping any failure on screen instead of losing it.
+    fn save(&mut self) -> bool {
+        let mut result = Ok(());
+        for todos in &mut self.lists {
+            if let Err(err) = todos.save_todos() {
+                result = Err(err);
+            }
+        }
+        self.report(result)
+    }
+
+    /// Show the outcome of a change in the Help area. Changes that were made stay
+    /// in memory even when writing them failed.
+    fn report(&mut self, result: Result<(), impl Into<ChangeError>>) -> bool {
+        match result {
+            Ok(()) => {
+                self.error = None;
+                self.quit_pending = false;
+                true
+            }
+            Err(err) => {
+                self.error = Some(err.into().to_string());
+                false
+            }
         }
     }
 }
@@ -37,48 +321,104 @@ impl Default for AppState {
 fn main() -> Result<()> {
     color_eyre::install()?;
 
+    let cli = Cli::parse();
+    let config = Config::load(cli.config.as_deref())?;
+    let workflow = Workflow::from_config(&config.statuses)
+        .map_err(|err| eyre!("Invalid statuses in the config file: {}", err))?;
+    workflow::install(workflow);
+    let data_file = config::resolve_data_file(&cli, &config);
+
+    let locator = ListLocator::new(data_file, config.backend, config.backup_count);
+    let mut todos = TodoList::new(&cli.list, locator.open(&cli.list));
+
+    if cli.list_backups {
+        for (position, backup) in todos.list_backups()?.iter().enumerate() {
+            println!("{:>3}  {}", position + 1, backup.display());
+        }
+        return Ok(());
+    }
+    if let Some(name) = &cli.restore_backup {
+        let backup = todos.restore_backup(name)?;
+        println!("Restored {} items from {}", todos.items.len(), backup.display());
+        return Ok(());
+    }
+
+    if let Some(command) = cli.command {
+        todos.read_todos()?;
+        if let Err(err) = commands::run(command, &mut todos, &locator) {
+            let code = commands::exit_code(&err);
+            if code == commands::exit_code::FAILURE {
+                return Err(err);
+            }
+            eprintln!("Error: {}", err);
+            std::process::exit(code);
+        }
+        return Ok(());
+    }
+
+    let mut names = locator.names()?;
+    if !names.contains(&cli.list) {
+        names.push(cli.list.clone());
+    }
+    let current = names.iter().position(|name| *name == cli.list).expect("the list was just added");
+    let lists = names
+        .into_iter()
+        .map(|name| {
+            let store = locator.open(&name);
+            TodoList::new(name, store)
+        })
+        .collect();
+
+    let (history, history_error) = match History::load(locator.history_path(), config.undo_limit) {
+        Ok(history) => (history, None),
+        Err(err) => (
+            History::new(locator.history_path(), config.undo_limit),
+            Some(format!("Starting a new undo history: {}", err)),
+        ),
+    };
+    let mut app_state = AppState::new(lists, current, history);
+    app_state.error = history_error;
+    if let Err(err) = app_state.todos_mut().read_todos() {
+        if !err.is_malformed() {
+            return Err(err.into());
+        }
+        app_state.error = Some(err.to_string());
+        app_state.mode = AppMode::Recovery;
+    }
+
     let terminal = ratatui::init();
-    let mut app_state = AppState::default();
-    app_state.todos.read_todos();
 
     let result = run_app(terminal, &mut app_state);
     ratatui::restore();
     result
 }
 
-fn run_app(mut terminal: DefaultTerminal, mut app_state: &mut AppState) -> Result<()> {
+fn run_app(mut terminal: DefaultTerminal, app_state: &mut AppState) -> Result<()> {
     loop {
-        terminal.draw(|frame| draw(frame, &mut app_state))?;
-
         if app_state.form.form_status.is_submitting() {
-            if let Some(new_todo) = app_state.form.get_submitted_todo() {
-                app_state.todos.items.push(new_todo);
-                app_state.todos.save_todos();
-                app_state.form.reset();
-                app_state.mode = AppMode::Normal;
-            }
+            handle_form_submit(app_state);
         }
+        app_state.record_changes();
+        app_state.sync_agenda();
 
-        if let Event::Key(key) = event::read()? {
-            match key.kind {
-                event::KeyEventKind::Press => match app_state.mode {
-                    AppMode::Normal => {
-                        if handle_normal_mode_input(key.code, key, &mut app_state) {
-                            break;
-                        }
-                    }
-                    AppMode::Editing => {
-                        if handle_editing_mode_input(key.code, key, &mut app_state) {
-                            break;
-                        }
-                    }
-                    AppMode::Adding => {
-                        if handle_adding_mode_input(key.code, key, &mut app_state) {
-                            break;
-                        }
-                    }
-                },
-                _ => {}
+        terminal.draw(|frame| draw(frame, app_state))?;
+
+        if let Event::Key(key) = event::read()?
+            && key.kind == event::KeyEventKind::Press
+        {
+            let quit = match app_state.mode {
+                AppMode::Normal => handle_normal_mode_input(key.code, key, app_state),
+                AppMode::Editing => handle_editing_mode_input(key.code, key, app_state),
+                AppMode::Adding => handle_adding_mode_input(key.code, key, app_state),
+                AppMode::Recovery => handle_recovery_mode_input(key.code, key, app_state),
+                AppMode::Moving => handle_moving_mode_input(key.code, key, app_state),
+                AppMode::Searching => handle_searching_mode_input(key.code, key, app_state),
+                AppMode::Filtering => handle_filtering_mode_input(key.code, key, app_state),
+                AppMode::SettingStatus => handle_setting_status_mode_input(key.code, key, app_state),
+                AppMode::Tagging => handle_tagging_mode_input(key.code, key, app_state),
+            };
+            if quit {
+                break;
             }
         }
     }
@@ -86,52 +426,274 @@ fn run_app(mut terminal: DefaultTerminal, mut app_state: &mut AppState) -> Resul
     Ok(())
 }
 
+fn handle_form_submit(app_state: &mut AppState) {
+    let Some(mut todo) = app_state.form.get_submitted_todo() else {
+        // Nothing valid to save yet, let the user keep working on the form
+        app_state.form.error = app_state.form.validate().err();
+        app_state.form.form_status.reset();
+        return;
+    };
+    match app_state.todos().resolve_ids(&app_state.form.dependency_prefixes()) {
+        Ok(depends_on) => todo.depends_on = depends_on,
+        Err(err) => {
+            app_state.form.error = Some(format!("Depends on: {}", err));
+            app_state.form.form_status.reset();
+            return;
+        }
+    }
+
+    let result = if app_state.form.is_editing_item() {
+        app_state.todos_mut().update_item(todo)
+    } else {
+        app_state.todos_mut().add_item(todo)
+    };
+    if let Err(err @ (ChangeError::Blocked(..) | ChangeError::DependencyCycle(_) | ChangeError::ParentCycle(_))) = result {
+        // Nothing was changed, let the user fix the form
+        app_state.form.error = Some(err.to_string());
+        app_state.form.form_status.reset();
+        return;
+    }
+    app_state.report(result);
+    app_state.form.reset();
+    app_state.mode = AppMode::Normal;
+}
+
+/// Save and quit the application. When saving fails the error is shown, and quitting
+/// again right away quits without saving.
+fn quit(app_state: &mut AppState, quit_pending: bool) -> bool {
+    if app_state.save() || quit_pending {
+        return true;
+    }
+    app_state.quit_pending = true;
+    app_state.error = app_state
+        .error
+        .take()
+        .map(|err| format!("{} (q or Ctrl+c again to quit without saving)", err));
+    false
+}
+
 fn handle_normal_mode_input(key: KeyCode, key_event: KeyEvent, app_state: &mut AppState) -> bool {
+    app_state.message = None;
+    // Only a quit straight after the failed one confirms it
+    let quit_pending = std::mem::take(&mut app_state.quit_pending);
 
     if key_event.modifiers.contains(KeyModifiers::CONTROL) {
         match key {
-            KeyCode::Char('c') => {
-                // Quit the application
-                app_state.todos.save_todos();
-                return true;
-            }
+            KeyCode::Char('c') => return quit(app_state, quit_pending),
             KeyCode::Char('a') => {
+                app_state.form.set_parent(None);
+                app_state.form.tags.set_known_tags(app_state.todos().tags());
                 app_state.mode = AppMode::Adding;
             }
             KeyCode::Char('e') => {
-                app_state.mode = AppMode::Editing;
+                app_state.edit_selected();
+            }
+            KeyCode::Char('r') => {
+                app_state.undo(true);
+                return false;
             }
             _ => {}
         }
     }
 
+    let handled = match app_state.view {
+        View::List => false,
+        View::Agenda => handle_agenda_input(key, app_state),
+        View::Board => handle_board_input(key, app_state),
+        View::Calendar => handle_calendar_input(key, app_state),
+    };
+    if handled {
+        return false;
+    }
+
     match key {
+        KeyCode::Char('q') => return quit(app_state, quit_pending),
+        KeyCode::Char('v') => {
+            app_state.view = app_state.view.next();
+        }
         KeyCode::Char('h') => {
-            app_state.todos.state.select(None);
+            app_state.todos_mut().state.select(None);
         }
         KeyCode::Char('j') => {
-            app_state.todos.state.select_previous();
+            app_state.todos_mut().state.select_previous();
         }
         KeyCode::Char('k') => {
-            app_state.todos.state.select_next();
+            app_state.todos_mut().state.select_next();
         }
         KeyCode::Char('l') => {
-            app_state.todos.state.select_first();
+            app_state.todos_mut().state.select_first();
         }
         KeyCode::Char('L') => {
-            app_state.todos.state.select_last();
+            app_state.todos_mut().state.select_last();
+        }
+        KeyCode::Char('D') if app_state.todos().marked_count() > 0 => {
+            let ids = app_state.todos().targets();
+            let reopen = app_state.todos().all_completed(&ids);
+            let result = app_state.todos_mut().complete_many(&ids, reopen);
+            app_state.summarize(if reopen { "Reopened" } else { "Completed" }, result);
         }
         KeyCode::Char('D') => {
-            app_state.todos.mark_completed();
+            let result = app_state.todos_mut().mark_completed();
+            app_state.report(result);
+        }
+        KeyCode::Char('R') if app_state.todos().marked_count() > 0 => {
+            let ids = app_state.todos().targets();
+            let result = app_state.todos_mut().remove_many(&ids);
+            app_state.summarize("Removed", result);
         }
         KeyCode::Char('R') => {
-            app_state.todos.remove_selected();
+            let result = app_state.todos_mut().remove_selected();
+            app_state.report(result);
+        }
+        KeyCode::Char(' ') => {
+            app_state.todos_mut().toggle_mark_selected();
+        }
+        KeyCode::Char('V') => {
+            app_state.todos_mut().mark_range();
+        }
+        KeyCode::Char('*') => {
+            app_state.todos_mut().mark_all();
+        }
+        KeyCode::Char('s') if !app_state.todos().targets().is_empty() => {
+            app_state.mode = AppMode::SettingStatus;
+        }
+        KeyCode::Char('#') if !app_state.todos().targets().is_empty() => {
+            app_state.tag_input.clear();
+            app_state.mode = AppMode::Tagging;
+        }
+        KeyCode::Char('>') => {
+            let result = app_state.todos_mut().advance_selected();
+            app_state.report(result);
+        }
+        KeyCode::Char('<') => {
+            let result = app_state.todos_mut().regress_selected();
+            app_state.report(result);
+        }
+        KeyCode::Char('p') => {
+            let result = app_state.todos_mut().cycle_selected_priority();
+            app_state.report(result);
+        }
+        KeyCode::Char('t') => {
+            app_state.todos_mut().cycle_tag_filter();
+        }
+        KeyCode::Char('u') => {
+            app_state.undo(false);
+        }
+        KeyCode::Char('A') => {
+            if let Some(item) = app_state.todos().get_selected() {
+                app_state.form.set_parent(Some(item.id));
+                app_state.form.tags.set_known_tags(app_state.todos().tags());
+                app_state.mode = AppMode::Adding;
+            }
+        }
+        KeyCode::Char('+') => {
+            app_state.todos_mut().expand_selected();
+        }
+        KeyCode::Char('-') => {
+            app_state.todos_mut().collapse_selected();
+        }
+        KeyCode::Tab => {
+            app_state.switch_list((app_state.current + 1) % app_state.lists.len());
+        }
+        KeyCode::BackTab => {
+            app_state.switch_list((app_state.current + app_state.lists.len() - 1) % app_state.lists.len());
+        }
+        KeyCode::Char('/') => {
+            app_state.mode = AppMode::Searching;
+        }
+        KeyCode::Char('f') => {
+            app_state.filter_input = app_state.todos().filter().map(Query::as_str).unwrap_or_default().to_string();
+            app_state.mode = AppMode::Filtering;
+        }
+        KeyCode::Char('n') => {
+            app_state.todos_mut().select_next_match();
+        }
+        KeyCode::Char('N') => {
+            app_state.todos_mut().select_previous_match();
+        }
+        KeyCode::Esc => {
+            // Esc clears the marks first, then the search
+            let had_marks = app_state.todos_mut().clear_marks();
+            if !had_marks {
+                app_state.todos_mut().set_search("");
+            }
+        }
+        KeyCode::Char('m') if app_state.todos().get_selected().is_some() => {
+            if app_state.lists.len() < 2 {
+                app_state.error = Some("There is no other list to move to, start one with --list <name>".to_string());
+            } else {
+                app_state.mode = AppMode::Moving;
+            }
         }
         _ => {}
     }
     false
 }
 
+/// Keys that mean something else on the board than in the list, returns whether the key was handled.
+fn handle_board_input(key: KeyCode, app_state: &mut AppState) -> bool {
+    let todos = &mut app_state.lists[app_state.current];
+    let board = &mut app_state.board;
+    match key {
+        KeyCode::Char('h') => board.select_column(todos, -1),
+        KeyCode::Char('l') => board.select_column(todos, 1),
+        KeyCode::Char('j') => board.select_row(todos, -1),
+        KeyCode::Char('k') => board.select_row(todos, 1),
+        KeyCode::Char('H') => {
+            let result = board.move_card(todos, -1);
+            app_state.report(result);
+        }
+        KeyCode::Char('L') => {
+            let result = board.move_card(todos, 1);
+            app_state.report(result);
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// Keys that mean something else in the agenda than in the list, returns whether the key was handled.
+fn handle_agenda_input(key: KeyCode, app_state: &mut AppState) -> bool {
+    let todos = &mut app_state.lists[app_state.current];
+    let agenda = &mut app_state.agenda;
+    match key {
+        KeyCode::Char('j') => agenda.select_row(todos, -1),
+        KeyCode::Char('k') => agenda.select_row(todos, 1),
+        KeyCode::Char('+') => agenda.expand_selected(todos),
+        KeyCode::Char('-') => agenda.collapse_selected(todos),
+        KeyCode::Enter if agenda.is_header_selected() => agenda.toggle_selected(todos),
+        KeyCode::Enter => app_state.edit_selected(),
+        _ => return false,
+    }
+    true
+}
+
+/// Keys that mean something else in the calendar than in the list, returns whether the key was handled.
+fn handle_calendar_input(key: KeyCode, app_state: &mut AppState) -> bool {
+    let todos = &mut app_state.lists[app_state.current];
+    let calendar = &mut app_state.calendar;
+    match key {
+        KeyCode::Char('h') => calendar.move_days(todos, -1),
+        KeyCode::Char('l') => calendar.move_days(todos, 1),
+        KeyCode::Char('H') => calendar.move_days(todos, -7),
+        KeyCode::Char('L') => calendar.move_days(todos, 7),
+        KeyCode::Char('[') => calendar.move_months(todos, -1),
+        KeyCode::Char(']') => calendar.move_months(todos, 1),
+        KeyCode::Char('T') => calendar.go_to(todos, dates::today()),
+        KeyCode::Char('j') => calendar.select_row(todos, -1),
+        KeyCode::Char('k') => calendar.select_row(todos, 1),
+        KeyCode::Char('r') => calendar.pick_up(todos),
+        KeyCode::Enter if calendar.is_moving() => {
+            let result = calendar.drop(todos);
+            app_state.report(result);
+        }
+        KeyCode::Enter => app_state.edit_selected(),
+        KeyCode::Esc if calendar.is_moving() => calendar.cancel(),
+        _ => return false,
+    }
+    true
+}
+
 fn handle_adding_mode_input(key: KeyCode, key_event: KeyEvent, app_state: &mut AppState) -> bool {
     match key {
         KeyCode::Esc => {
@@ -144,22 +706,166 @@ fn handle_adding_mode_input(key: KeyCode, key_event: KeyEvent, app_state: &mut A
     false
 }
 
-fn handle_editing_mode_input(key: KeyCode, _key_event: KeyEvent, app_state: &mut AppState) -> bool {
+fn handle_editing_mode_input(key: KeyCode, key_event: KeyEvent, app_state: &mut AppState) -> bool {
+    match key {
+        KeyCode::Esc => {
+            app_state.form.reset();
+            app_state.mode = AppMode::Normal;
+        }
+        _ => {
+            app_state.form.on_key_press(key, key_event);
+        }
+    }
+    false
+}
+
+fn handle_searching_mode_input(key: KeyCode, key_event: KeyEvent, app_state: &mut AppState) -> bool {
+    let mut search = app_state.todos().search().to_string();
+    match key {
+        KeyCode::Esc => {
+            search.clear();
+            app_state.mode = AppMode::Normal;
+        }
+        KeyCode::Enter => {
+            app_state.mode = AppMode::Normal;
+            return false;
+        }
+        KeyCode::Backspace => {
+            search.pop();
+        }
+        KeyCode::Char(c) if !key_event.modifiers.contains(KeyModifiers::CONTROL) => search.push(c),
+        _ => return false,
+    }
+    app_state.todos_mut().set_search(&search);
+    false
+}
+
+fn handle_filtering_mode_input(key: KeyCode, key_event: KeyEvent, app_state: &mut AppState) -> bool {
+    match key {
+        KeyCode::Esc => {
+            app_state.mode = AppMode::Normal;
+        }
+        KeyCode::Enter => {
+            // Invalid queries stay in the prompt, the error is shown below them
+            if let Ok(query) = Query::parse(&app_state.filter_input, dates::today()) {
+                let filter = (!query.as_str().is_empty()).then_some(query);
+                app_state.todos_mut().set_filter(filter);
+                app_state.mode = AppMode::Normal;
+            }
+        }
+        KeyCode::Backspace => {
+            app_state.filter_input.pop();
+        }
+        KeyCode::Char(c) if !key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+            app_state.filter_input.push(c);
+        }
+        _ => {}
+    }
+    false
+}
+
+fn handle_setting_status_mode_input(key: KeyCode, _key_event: KeyEvent, app_state: &mut AppState) -> bool {
+    let statuses = workflow::workflow().statuses();
+    let status = match key {
+        KeyCode::Esc => {
+            app_state.mode = AppMode::Normal;
+            return false;
+        }
+        // Statuses are numbered from 1 in the prompt
+        KeyCode::Char(c) => c
+            .to_digit(10)
+            .and_then(|digit| (digit as usize).checked_sub(1))
+            .and_then(|index| statuses.get(index)),
+        _ => None,
+    };
+    if let Some(status) = status {
+        let ids = app_state.todos().targets();
+        let result = app_state.todos_mut().update_many(&ids, |item| item.status = status.clone());
+        app_state.summarize(&format!("Set {} on", status.name()), result);
+        app_state.mode = AppMode::Normal;
+    }
+    false
+}
+
+fn handle_tagging_mode_input(key: KeyCode, key_event: KeyEvent, app_state: &mut AppState) -> bool {
     match key {
         KeyCode::Esc => {
             app_state.mode = AppMode::Normal;
         }
+        KeyCode::Enter => {
+            let mut added = Vec::new();
+            let mut removed = Vec::new();
+            for word in app_state.tag_input.split([',', ' ']).filter(|word| !word.is_empty()) {
+                match word.strip_prefix('-') {
+                    Some(tag) => removed.push(tag),
+                    None => added.push(word.trim_start_matches('+')),
+                }
+            }
+            let added = todo::parse_tags(&added.join(","));
+            let removed = todo::parse_tags(&removed.join(","));
+            let ids = app_state.todos().targets();
+            let result = app_state.todos_mut().update_many(&ids, |item| {
+                item.tags.extend(added.iter().cloned());
+                item.tags.retain(|tag| !removed.contains(tag));
+            });
+            app_state.summarize("Retagged", result);
+            app_state.mode = AppMode::Normal;
+        }
+        KeyCode::Backspace => {
+            app_state.tag_input.pop();
+        }
+        KeyCode::Char(c) if !key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+            app_state.tag_input.push(c);
+        }
         _ => {}
     }
     false
 }
 
+fn handle_moving_mode_input(key: KeyCode, _key_event: KeyEvent, app_state: &mut AppState) -> bool {
+    let target = match key {
+        KeyCode::Esc => {
+            app_state.mode = AppMode::Normal;
+            return false;
+        }
+        // Lists are numbered from 1 in the tab bar
+        KeyCode::Char(c) => c.to_digit(10).and_then(|digit| (digit as usize).checked_sub(1)),
+        _ => None,
+    };
+    if let Some(target) = target.filter(|&target| target < app_state.lists.len() && target != app_state.current) {
+        app_state.move_selected(target);
+        app_state.mode = AppMode::Normal;
+    }
+    false
+}
+
+fn handle_recovery_mode_input(key: KeyCode, key_event: KeyEvent, app_state: &mut AppState) -> bool {
+    let result = match key {
+        KeyCode::Char('b') => app_state.todos_mut().read_backup(),
+        KeyCode::Char('e') => app_state.todos_mut().start_empty(),
+        KeyCode::Char('q') | KeyCode::Esc => return true,
+        KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => return true,
+        _ => return false,
+    };
+
+    match result {
+        Ok(()) => {
+            app_state.error = None;
+            app_state.mode = AppMode::Normal;
+        }
+        Err(err) => {
+            app_state.error = Some(err.to_string());
+        }
+    }
+    false
+}
+
 fn draw(frame: &mut Frame, app_state: &mut AppState) {
     let [main_layout] = Layout::vertical([Constraint::Fill(1)])
         .margin(1)
         .areas(frame.area());
 
-    let [list_box, tips] = Layout::vertical([Constraint::Fill(1), Constraint::Length(3)])
+    let [tabs_area, list_box, tips] = Layout::vertical([Constraint::Length(1), Constraint::Fill(1), Constraint::Length(6)])
         .margin(1)
         .areas(main_layout);
 
@@ -173,46 +879,133 @@ fn draw(frame: &mut Frame, app_state: &mut AppState) {
         .style(Style::new().bg(Color::Magenta))
         .render(main_layout, frame.buffer_mut());
 
-    match app_state.mode {
-        AppMode::Normal => {
-            let items: Vec<String> = app_state.todos.get_list_to_display();
+    let titles = app_state
+        .lists
+        .iter()
+        .enumerate()
+        .map(|(position, todos)| format!(" {} {} ", position + 1, todos.name()));
+    frame.render_widget(
+        Tabs::new(titles)
+            .select(app_state.current)
+            .padding("", "")
+            .highlight_style(Style::new().bg(Color::Blue)),
+        tabs_area,
+    );
 
-            let toto_list = List::new(items)
-                .block(
+    if let AppMode::Normal
+    | AppMode::Moving
+    | AppMode::Searching
+    | AppMode::Filtering
+    | AppMode::SettingStatus
+    | AppMode::Tagging = app_state.mode
+    {
+        let mut filters = String::new();
+        if let Some(tag) = app_state.todos().tag_filter() {
+            filters.push_str(&format!(" #{}", tag));
+        }
+        if let Some(filter) = app_state.todos().filter() {
+            filters.push_str(&format!(" [{}]", filter.as_str()));
+        }
+        if !app_state.todos().search().is_empty() {
+            filters.push_str(&format!(" /{}", app_state.todos().search()));
+        }
+        if app_state.todos().marked_count() > 0 {
+            filters.push_str(&format!(" ({} marked)", app_state.todos().marked_count()));
+        }
+        if app_state.todos().is_marking_range() {
+            filters.push_str(" (V again to mark the range)");
+        }
+        match app_state.view {
+            View::Agenda => {
+                let [agenda_area, read_area] =
+                    Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(list_box);
+                let title = format!("Agenda{}", filters);
+                app_state.agenda.render(app_state.todos(), agenda_area, frame.buffer_mut(), &title);
+                render_read(frame, app_state, read_area);
+            }
+            View::List => render_list(frame, app_state, todo_list_area, todo_area, &format!("List{}", filters)),
+            View::Calendar => {
+                let title = format!("Calendar{}", filters);
+                let block = Block::bordered()
+                    .title(title.to_span().into_centered_line())
+                    .border_type(BorderType::Rounded);
+                app_state.calendar.render(app_state.todos(), block.inner(list_box), frame.buffer_mut());
+                frame.render_widget(block, list_box);
+            }
+            View::Board => {
+                let title = format!("Board{}", filters);
+                let block = Block::bordered()
+                    .title(title.to_span().into_centered_line())
+                    .border_type(BorderType::Rounded);
+                app_state.board.render(app_state.todos(), block.inner(list_box), frame.buffer_mut());
+                frame.render_widget(block, list_box);
+            }
+        }
+    }
+
+    match app_state.mode {
+        AppMode::Normal if app_state.error.is_some() => {
+            render_error(frame, app_state, tips);
+        }
+        AppMode::Normal if let Some(message) = &app_state.message => {
+            frame.render_widget(
+                Paragraph::new(message.as_str()).wrap(Wrap { trim: true }).block(
                     Block::bordered()
-                        .title("List".to_span().into_centered_line())
+                        .title("Done".to_span().into_centered_line())
                         .border_type(BorderType::Rounded),
-                )
-                .highlight_symbol(">")
-                .highlight_style(Style::new().bg(Color::Blue));
-
-            StatefulWidget::render(
-                toto_list,
-                todo_list_area,
-                frame.buffer_mut(),
-                &mut app_state.todos.state,
+                ),
+                tips,
             );
-
+        }
+        AppMode::SettingStatus => {
+            let statuses: Vec<String> = workflow::workflow()
+                .statuses()
+                .iter()
+                .enumerate()
+                .map(|(position, status)| format!("{} -> {}", position + 1, status.name()))
+                .collect();
+            let title = format!("Set the status of {} todos", app_state.todos().targets().len());
             frame.render_widget(
-                Paragraph::new(app_state.todos.get_selected_item_display()).block(
+                Paragraph::new(format!("{} | Esc -> cancel", statuses.join(" | ")))
+                    .wrap(Wrap { trim: true })
+                    .block(
+                        Block::bordered()
+                            .title(title.to_span().into_centered_line())
+                            .border_type(BorderType::Rounded),
+                    ),
+                tips,
+            );
+        }
+        AppMode::Tagging => {
+            let title = format!(
+                "Tags of {} todos | tag or +tag adds, -tag removes | Enter -> apply | Esc -> cancel",
+                app_state.todos().targets().len()
+            );
+            frame.render_widget(
+                Paragraph::new(format!("#> {}", app_state.tag_input)).block(
                     Block::bordered()
-                        .title("Read".to_span().into_centered_line())
+                        .title(title.to_span().into_centered_line())
                         .border_type(BorderType::Rounded),
                 ),
-                todo_area,
+                tips,
             );
+            let column = (app_state.tag_input.chars().count() + 3) as u16;
+            frame.set_cursor_position((tips.x + 1 + column, tips.y + 1));
         }
-        AppMode::Editing => {}
-        AppMode::Adding => {}
-    }
-
-    match app_state.mode {
         AppMode::Normal => {
+            let help = match app_state.view {
+                View::Agenda => "q -> quit | u / Ctrl+r -> undo/redo | j / k -> previous/next row | Enter -> open todo or collapse/expand section | + / - -> expand/collapse section | v -> list view | Tab / Shift+Tab -> next/previous list | / -> search | f -> filter | t -> filter by tag | D -> complete/reopen | > / < -> next/previous status | p -> cycle priority | R -> remove with subtasks | m -> move to list | Ctrl+a -> add item | Ctrl+e -> edit selected",
+                View::List => "q -> quit | u / Ctrl+r -> undo/redo | j / k -> previous/next | l / L -> first/last | h -> deselect | v -> board view | Tab / Shift+Tab -> next/previous list | / -> search | n / N -> next/previous match | f -> filter | t -> filter by tag | Esc -> clear marks, then search | space -> mark | V -> mark range | * -> mark all | D -> complete/reopen | R -> remove with subtasks | m -> move to list | s -> set status | # -> add/remove tags | D, R, m, s and # act on the marked todos | > / < -> next/previous status | p -> cycle priority | + / - -> expand/collapse | Ctrl+a -> add | A -> add subtask | Ctrl+e -> edit",
+                View::Calendar if app_state.calendar.is_moving() => "h / l -> previous/next day | H / L -> previous/next week | [ / ] -> previous/next month | T -> today | Enter -> reschedule to this day | Esc -> cancel",
+                View::Calendar => "q -> quit | u / Ctrl+r -> undo/redo | h / l -> previous/next day | H / L -> previous/next week | [ / ] -> previous/next month | T -> today | j / k -> previous/next todo of the day | Enter -> open | r -> reschedule | v -> agenda view | Tab / Shift+Tab -> next/previous list | / -> search | f -> filter | t -> filter by tag | D -> complete/reopen | R -> remove with subtasks | Ctrl+a -> add item",
+                View::Board => "q -> quit | u / Ctrl+r -> undo/redo | h / l -> previous/next column | j / k -> previous/next card | H / L -> move card to the previous/next column | v -> calendar view | Tab / Shift+Tab -> next/previous list | m -> move to list | / -> search | f -> filter | t -> filter by tag | D -> complete/reopen | p -> cycle priority | R -> remove with subtasks | Ctrl+a -> add item | A -> add subtask | Ctrl+e -> edit card",
+            };
             frame.render_widget(
                 // q -> quit
                 // h -> deselect | j -> select previous | k -> select next | l -> select first |
                 // a -> add item
-                Paragraph::new("q -> quit | h -> deselect | j -> select previous | k -> select next | l -> select first | L -> select last | a -> add item")
+                Paragraph::new(help)
+                    .wrap(Wrap { trim: true })
                     .block(
                         Block::bordered()
                             .title("Help".to_span().into_centered_line())
@@ -222,50 +1015,164 @@ fn draw(frame: &mut Frame, app_state: &mut AppState) {
             );
         }
         AppMode::Editing => {
+            render_form(frame, app_state, list_box, tips, "Editing");
+        }
+        AppMode::Adding => {
+            let parent = app_state.form.parent().and_then(|id| app_state.todos().items.iter().find(|item| item.id == id));
+            let title = match parent {
+                Some(parent) => format!("Adding a subtask of {}", parent.title),
+                None => "Adding".to_string(),
+            };
+            render_form(frame, app_state, list_box, tips, &title);
+        }
+        AppMode::Searching => {
+            let search = app_state.todos().search();
+            let matches = match app_state.todos().match_count() {
+                1 => "1 match".to_string(),
+                count => format!("{} matches", count),
+            };
             frame.render_widget(
-                Paragraph::new("Editing mode not implemented yet.").block(
+                Paragraph::new(format!("/{}", search)).block(
                     Block::bordered()
-                        .title("Editing".to_span().into_centered_line())
-                        .style(Style::new().bg(Color::Red))
+                        .title(format!("Search | {} | Enter -> keep | Esc -> clear", matches).to_span().into_centered_line())
                         .border_type(BorderType::Rounded),
                 ),
                 tips,
             );
-            return;
+            frame.set_cursor_position((tips.x + 2 + search.chars().count() as u16, tips.y + 1));
         }
-        AppMode::Adding => {
-            let color = if app_state.form.form_status.is_editing() {
-                Color::Magenta
-            } else {
-                Color::Red
-            };
-
-            Block::bordered()
-                .border_type(BorderType::Rounded)
-                .title(
-                    "Adding mode not fully implemented yet"
-                        .to_span()
-                        .into_centered_line(),
-                )
-                .style(Style::new().bg(color))
-                .render(list_box, frame.buffer_mut());
-
-            app_state.form.render(list_box, frame);
-
+        AppMode::Filtering => {
+            let mut text = Text::from(format!("f> {}", app_state.filter_input));
+            if let Err(err) = Query::parse(&app_state.filter_input, dates::today()) {
+                let caret = format!("{}^ {}", " ".repeat(err.column + 2), err.message);
+                text.push_line(Line::styled(caret, Style::new().fg(Color::LightRed)));
+            }
             frame.render_widget(
-                Paragraph::new(app_state.form.form_status.to_str()).block(
+                Paragraph::new(text).block(
                     Block::bordered()
-                        .title(
-                            "Adding mode not fully implemented yet"
-                                .to_span()
-                                .into_centered_line(),
-                        )
-                        .style(Style::new().bg(Color::Red))
+                        .title("Filter | Enter -> apply, empty to clear | Esc -> cancel".to_span().into_centered_line())
                         .border_type(BorderType::Rounded),
                 ),
                 tips,
             );
-            return;
+            let column = (app_state.filter_input.chars().count() + 3) as u16;
+            frame.set_cursor_position((tips.x + 1 + column, tips.y + 1));
+        }
+        AppMode::Moving => {
+            let title = app_state.todos().get_selected().map(|item| item.title.clone()).unwrap_or_default();
+            let targets: Vec<String> = app_state
+                .lists
+                .iter()
+                .enumerate()
+                .filter(|&(position, _)| position != app_state.current)
+                .map(|(position, todos)| format!("{} -> {}", position + 1, todos.name()))
+                .collect();
+            frame.render_widget(
+                Paragraph::new(format!("{} | Esc -> cancel", targets.join(" | ")))
+                    .wrap(Wrap { trim: true })
+                    .block(
+                        Block::bordered()
+                            .title(format!("Move {} and its subtasks to", title).to_span().into_centered_line())
+                            .border_type(BorderType::Rounded)
+                    ),
+                tips
+            );
+        }
+        AppMode::Recovery => {
+            frame.render_widget(
+                Paragraph::new(
+                    format!("The {} list could not be loaded.\n\n\
+                     b -> open the most recent backup\n\
+                     e -> start with an empty list\n\
+                     q -> quit without changing anything\n\n\
+                     The unreadable file is kept with a .corrupt extension when you continue.",
+                    app_state.todos().name()
+                ))
+                .block(
+                    Block::bordered()
+                        .title("Recovery".to_span().into_centered_line())
+                        .border_type(BorderType::Rounded),
+                ),
+                list_box,
+            );
+            render_error(frame, app_state, tips);
         }
     }
 }
+
+fn render_list(frame: &mut Frame, app_state: &mut AppState, list_area: Rect, read_area: Rect, title: &str) {
+    let items = app_state.todos().get_list_to_display();
+    let toto_list = List::new(items)
+        .block(
+            Block::bordered()
+                .title(title.to_span().into_centered_line())
+                .border_type(BorderType::Rounded),
+        )
+        .highlight_symbol(">")
+        .highlight_style(Style::new().bg(Color::Blue));
+
+    StatefulWidget::render(
+        toto_list,
+        list_area,
+        frame.buffer_mut(),
+        &mut app_state.lists[app_state.current].state,
+    );
+
+    render_read(frame, app_state, read_area);
+}
+
+fn render_read(frame: &mut Frame, app_state: &AppState, area: Rect) {
+    frame.render_widget(
+        Paragraph::new(app_state.todos().get_selected_item_display()).block(
+            Block::bordered()
+                .title("Read".to_span().into_centered_line())
+                .border_type(BorderType::Rounded),
+        ),
+        area,
+    );
+}
+
+fn render_error(frame: &mut Frame, app_state: &AppState, tips: Rect) {
+    frame.render_widget(
+        Paragraph::new(app_state.error.as_deref().unwrap_or_default()).block(
+            Block::bordered()
+                .title("Error".to_span().into_centered_line())
+                .style(Style::new().bg(Color::Red))
+                .border_type(BorderType::Rounded),
+        ),
+        tips,
+    );
+}
+
+fn render_form(frame: &mut Frame, app_state: &AppState, form_area: Rect, tips: Rect, title: &str) {
+    let color = if app_state.form.form_status.is_editing() {
+        Color::Magenta
+    } else {
+        Color::Red
+    };
+
+    Block::bordered()
+        .border_type(BorderType::Rounded)
+        .title(title.to_span().into_centered_line())
+        .style(Style::new().bg(color))
+        .render(form_area, frame.buffer_mut());
+
+    app_state.form.render(form_area, frame);
+
+    frame.render_widget(
+        Paragraph::new(match &app_state.form.error {
+            Some(err) => format!("{} | {}", app_state.form.form_status.to_str(), err),
+            None => format!(
+                "{} | Enter -> edit field | Tab -> next field | Ctrl+s -> save | Esc -> cancel",
+                app_state.form.form_status.to_str()
+            ),
+        })
+        .block(
+            Block::bordered()
+                .title(title.to_span().into_centered_line())
+                .style(Style::new().bg(Color::Red))
+                .border_type(BorderType::Rounded),
+        ),
+        tips,
+    );
+}
@@ -0,0 +1,232 @@
+use std::io::{self, Write};
+
+use clap::ValueEnum;
+use serde_json::{Map, Value, json};
+
+use crate::{dates, form::form_inputs::enum_field::EnumDisplay, todo::{self, TodoItem}};
+
+/// Version of the JSON / NDJSON records printed by the CLI, see the readme.
+/// Bump it whenever a field is renamed, removed or changes type; adding fields does not.
+pub const OUTPUT_SCHEMA_VERSION: u64 = 1;
+
+#[derive(ValueEnum, Debug, Default, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    /// Aligned columns for people
+    #[default]
+    Table,
+    /// One JSON document: `{"schema_version": 1, "items": [...]}`
+    Json,
+    /// One JSON object per line
+    Ndjson,
+}
+
+/// Fields are selected on the command line by their JSON name.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+#[value(rename_all = "snake_case")]
+pub enum OutputField {
+    Id,
+    Title,
+    Description,
+    Completed,
+    Status,
+    Priority,
+    Tags,
+    ParentId,
+    DependsOn,
+    Due,
+    DueTime,
+    Recurrence,
+    CreatedAt,
+    UpdatedAt,
+    CompletedAt,
+}
+
+impl OutputField {
+    const ALL: &[OutputField] = &[
+        OutputField::Id,
+        OutputField::Title,
+        OutputField::Description,
+        OutputField::Completed,
+        OutputField::Status,
+        OutputField::Priority,
+        OutputField::Tags,
+        OutputField::ParentId,
+        OutputField::DependsOn,
+        OutputField::Due,
+        OutputField::DueTime,
+        OutputField::Recurrence,
+        OutputField::CreatedAt,
+        OutputField::UpdatedAt,
+        OutputField::CompletedAt,
+    ];
+    const TABLE: &[OutputField] = &[
+        OutputField::Id,
+        OutputField::Completed,
+        OutputField::Status,
+        OutputField::Priority,
+        OutputField::Due,
+        OutputField::Title,
+        OutputField::Tags,
+    ];
+
+    /// Name of the field in JSON output, part of the output schema.
+    fn name(&self) -> &'static str {
+        match self {
+            OutputField::Id => "id",
+            OutputField::Title => "title",
+            OutputField::Description => "description",
+            OutputField::Completed => "completed",
+            OutputField::Status => "status",
+            OutputField::Priority => "priority",
+            OutputField::Tags => "tags",
+            OutputField::ParentId => "parent_id",
+            OutputField::DependsOn => "depends_on",
+            OutputField::Due => "due",
+            OutputField::DueTime => "due_time",
+            OutputField::Recurrence => "recurrence",
+            OutputField::CreatedAt => "created_at",
+            OutputField::UpdatedAt => "updated_at",
+            OutputField::CompletedAt => "completed_at",
+        }
+    }
+
+    fn value(&self, item: &TodoItem) -> Value {
+        match self {
+            OutputField::Id => json!(item.id),
+            OutputField::Title => json!(item.title),
+            OutputField::Description => json!(item.description),
+            OutputField::Completed => json!(item.is_completed()),
+            OutputField::Status => json!(item.status.to_str()),
+            OutputField::Priority => json!(item.priority.to_str()),
+            OutputField::Tags => json!(item.tags),
+            OutputField::ParentId => json!(item.parent_id),
+            OutputField::DependsOn => json!(item.depends_on),
+            OutputField::Due => json!(item.due.map(|due| due.format("%Y-%m-%d").to_string())),
+            OutputField::DueTime => json!(item.due_time.map(|time| time.format("%H:%M").to_string())),
+            OutputField::Recurrence => json!(item.recurrence),
+            OutputField::CreatedAt => json!(item.created_at),
+            OutputField::UpdatedAt => json!(item.updated_at),
+            OutputField::CompletedAt => json!(item.completed_at),
+        }
+    }
+
+    fn text(&self, item: &TodoItem) -> String {
+        match self.value(item) {
+            Value::String(value) => value,
+            Value::Null => String::new(),
+            value => value.to_string(),
+        }
+    }
+
+    /// Shorter rendering of the value for table columns.
+    fn cell(&self, item: &TodoItem) -> String {
+        match self {
+            OutputField::Id => todo::short_id(item.id),
+            OutputField::Completed => if item.is_completed() { "[✓]" } else { "[✗]" }.to_string(),
+            OutputField::Priority if item.priority.is_none() => String::new(),
+            OutputField::Tags => item.tags.iter().cloned().collect::<Vec<_>>().join(","),
+            OutputField::ParentId => item.parent_id.map(todo::short_id).unwrap_or_default(),
+            OutputField::DependsOn => item.depends_on.iter().map(|id| todo::short_id(*id)).collect::<Vec<_>>().join(","),
+            OutputField::Due => match item.due {
+                Some(due) => dates::format_due(due, item.due_time),
+                None => String::new(),
+            },
+            OutputField::CreatedAt => dates::format_timestamp(item.created_at),
+            OutputField::UpdatedAt => dates::format_timestamp(item.updated_at),
+            OutputField::CompletedAt => item.completed_at.map(dates::format_timestamp).unwrap_or_default(),
+            _ => self.text(item),
+        }
+    }
+
+    fn header(&self) -> &'static str {
+        match self {
+            OutputField::Completed => "done",
+            _ => self.name(),
+        }
+    }
+}
+
+/// Print `items` with the chosen fields, or the default fields of the format when none are given.
+pub fn print_items(
+    out: &mut impl Write,
+    items: &[TodoItem],
+    format: OutputFormat,
+    fields: Option<&[OutputField]>,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Table => print_table(out, items, fields.unwrap_or(OutputField::TABLE)),
+        OutputFormat::Json => {
+            let fields = fields.unwrap_or(OutputField::ALL);
+            let records: Vec<Value> = items.iter().map(|item| record(item, fields)).collect();
+            let document = json!({
+                "schema_version": OUTPUT_SCHEMA_VERSION,
+                "items": records,
+            });
+            writeln!(out, "{}", serde_json::to_string_pretty(&document)?)
+        }
+        OutputFormat::Ndjson => {
+            let fields = fields.unwrap_or(OutputField::ALL);
+            for item in items {
+                writeln!(out, "{}", record(item, fields))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Print a single item, as `field: value` lines in table format so long values stay readable.
+pub fn print_item(
+    out: &mut impl Write,
+    item: &TodoItem,
+    format: OutputFormat,
+    fields: Option<&[OutputField]>,
+) -> io::Result<()> {
+    let fields = fields.unwrap_or(OutputField::ALL);
+    if format != OutputFormat::Table {
+        return print_items(out, std::slice::from_ref(item), format, Some(fields));
+    }
+
+    let width = fields.iter().map(|field| field.name().len()).max().unwrap_or_default() + 1;
+    for field in fields {
+        let name = format!("{}:", field.name());
+        writeln!(out, "{:<width$} {}", name, field.text(item), width = width)?;
+    }
+    Ok(())
+}
+
+fn record(item: &TodoItem, fields: &[OutputField]) -> Value {
+    let record: Map<String, Value> = fields
+        .iter()
+        .map(|field| (field.name().to_string(), field.value(item)))
+        .collect();
+    Value::Object(record)
+}
+
+fn print_table(out: &mut impl Write, items: &[TodoItem], fields: &[OutputField]) -> io::Result<()> {
+    let rows: Vec<Vec<String>> = items
+        .iter()
+        .map(|item| fields.iter().map(|field| field.cell(item)).collect())
+        .collect();
+    let widths: Vec<usize> = fields
+        .iter()
+        .enumerate()
+        .map(|(column, field)| {
+            rows.iter()
+                .map(|row| row[column].chars().count())
+                .chain([field.header().len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let headers: Vec<String> = fields.iter().map(|field| field.header().to_uppercase()).collect();
+    for row in [headers].iter().chain(&rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        writeln!(out, "{}", line.join("  ").trim_end())?;
+    }
+    Ok(())
+}
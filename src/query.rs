@@ -0,0 +1,499 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
+
+use crate::{dates, todo::{Priority, TodoItem}, todo_list::TodoList};
+
+/// A filter over the todos, written like a search query:
+///
+/// `status:InProgress tag:backend due<2026-11-01 -completed "free text"`
+///
+/// Terms separated by spaces must all match, `or` between terms matches either side,
+/// parentheses group terms and a leading `-` negates a term.
+#[derive(Debug, Clone)]
+pub struct Query {
+    text: String,
+    expr: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    /// The empty query
+    All,
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    Status(String),
+    Tag(String),
+    Priority(Comparison, Priority),
+    Date(DateField, Comparison, NaiveDate),
+    Title(String),
+    Description(String),
+    /// Title or description
+    Text(String),
+    Flag(Flag),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DateField {
+    Due,
+    Created,
+    Updated,
+    Completed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Flag {
+    Completed,
+    Open,
+    Blocked,
+    Overdue,
+    Recurring,
+}
+
+const FLAGS: [(&str, Flag); 6] = [
+    ("completed", Flag::Completed),
+    ("done", Flag::Completed),
+    ("open", Flag::Open),
+    ("blocked", Flag::Blocked),
+    ("overdue", Flag::Overdue),
+    ("recurring", Flag::Recurring),
+];
+
+/// Operators in the order they are looked for, two character ones first.
+const OPERATORS: [(&str, Comparison); 6] = [
+    ("<=", Comparison::LessOrEqual),
+    (">=", Comparison::GreaterOrEqual),
+    ("<", Comparison::Less),
+    (">", Comparison::Greater),
+    ("=", Comparison::Equal),
+    (":", Comparison::Equal),
+];
+
+/// Why a query could not be parsed, `column` counts characters from 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryError {
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: {}", self.column, self.message)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+impl Query {
+    /// Parse `text`, relative dates like `today` or `+7d` are resolved against `today`.
+    pub fn parse(text: &str, today: NaiveDate) -> Result<Query, QueryError> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            depth: 0,
+            today,
+            end: text.chars().count() + 1,
+        };
+        let expr = match parser.tokens.is_empty() {
+            true => Expr::All,
+            false => parser.or()?,
+        };
+        if let Some(token) = parser.tokens.get(parser.position) {
+            return Err(QueryError {
+                column: token.column,
+                message: "unexpected )".to_string(),
+            });
+        }
+        Ok(Query {
+            text: text.trim().to_string(),
+            expr,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn matches(&self, item: &TodoItem, todos: &TodoList) -> bool {
+        self.expr.matches(item, todos)
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Query::parse(text, dates::today())
+    }
+}
+
+impl Expr {
+    fn matches(&self, item: &TodoItem, todos: &TodoList) -> bool {
+        match self {
+            Expr::All => true,
+            Expr::And(exprs) => exprs.iter().all(|expr| expr.matches(item, todos)),
+            Expr::Or(exprs) => exprs.iter().any(|expr| expr.matches(item, todos)),
+            Expr::Not(expr) => !expr.matches(item, todos),
+            Expr::Status(name) => item.status.name().eq_ignore_ascii_case(name),
+            Expr::Tag(tag) => item.tags.contains(tag),
+            Expr::Priority(comparison, priority) => comparison.holds(item.priority.cmp(priority)),
+            Expr::Date(field, comparison, date) => {
+                let local = |timestamp: DateTime<Utc>| timestamp.with_timezone(&Local).date_naive();
+                let value = match field {
+                    DateField::Due => item.due,
+                    DateField::Created => Some(local(item.created_at)),
+                    DateField::Updated => Some(local(item.updated_at)),
+                    DateField::Completed => item.completed_at.map(local),
+                };
+                value.is_some_and(|value| comparison.holds(value.cmp(date)))
+            }
+            Expr::Title(text) => contains(&item.title, text),
+            Expr::Description(text) => contains(&item.description, text),
+            Expr::Text(text) => contains(&item.title, text) || contains(&item.description, text),
+            Expr::Flag(Flag::Completed) => item.is_completed(),
+            Expr::Flag(Flag::Open) => !item.is_completed(),
+            Expr::Flag(Flag::Blocked) => !item.is_completed() && todos.is_blocked(item),
+            Expr::Flag(Flag::Overdue) => item.is_overdue(dates::now()),
+            Expr::Flag(Flag::Recurring) => item.recurrence.is_some(),
+        }
+    }
+}
+
+impl Comparison {
+    fn holds(&self, ordering: std::cmp::Ordering) -> bool {
+        match self {
+            Comparison::Less => ordering.is_lt(),
+            Comparison::LessOrEqual => ordering.is_le(),
+            Comparison::Equal => ordering.is_eq(),
+            Comparison::GreaterOrEqual => ordering.is_ge(),
+            Comparison::Greater => ordering.is_gt(),
+        }
+    }
+}
+
+fn contains(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Open,
+    Close,
+    Not,
+    Or,
+    /// A bare word, quotes inside it are already removed
+    Word(String),
+    /// Text in quotes, always free text
+    Quoted(String),
+}
+
+#[derive(Debug)]
+struct Token {
+    kind: TokenKind,
+    column: usize,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().enumerate().peekable();
+    while let Some(&(position, c)) = chars.peek() {
+        let column = position + 1;
+        let kind = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '(' => {
+                chars.next();
+                TokenKind::Open
+            }
+            ')' => {
+                chars.next();
+                TokenKind::Close
+            }
+            '-' => {
+                chars.next();
+                TokenKind::Not
+            }
+            '"' => {
+                chars.next();
+                TokenKind::Quoted(quoted(&mut chars, column)?)
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&(_, c)) = chars.peek() {
+                    match c {
+                        c if c.is_whitespace() || c == '(' || c == ')' => break,
+                        '"' => {
+                            let (position, _) = chars.next().expect("peeked");
+                            word.push_str(&quoted(&mut chars, position + 1)?);
+                        }
+                        c => {
+                            chars.next();
+                            word.push(c);
+                        }
+                    }
+                }
+                match word.eq_ignore_ascii_case("or") {
+                    true => TokenKind::Or,
+                    false => TokenKind::Word(word),
+                }
+            }
+        };
+        tokens.push(Token { kind, column });
+    }
+    Ok(tokens)
+}
+
+/// The rest of a quoted string whose opening quote was at `column`.
+fn quoted(chars: &mut impl Iterator<Item = (usize, char)>, column: usize) -> Result<String, QueryError> {
+    let mut text = String::new();
+    for (_, c) in chars.by_ref() {
+        if c == '"' {
+            return Ok(text);
+        }
+        text.push(c);
+    }
+    Err(QueryError {
+        column,
+        message: "missing closing \"".to_string(),
+    })
+}
+
+/// Deepest nesting of `(` and `-` a query may have, so a long prompt can't overflow the stack
+const MAX_DEPTH: usize = 64;
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// Open groups and negations around the current term
+    depth: usize,
+    today: NaiveDate,
+    /// Column just past the end of the text, for errors about missing terms
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.position).map(|token| &token.kind)
+    }
+
+    fn column(&self) -> usize {
+        self.tokens.get(self.position).map_or(self.end, |token| token.column)
+    }
+
+    fn or(&mut self) -> Result<Expr, QueryError> {
+        let mut exprs = vec![self.and()?];
+        while self.peek() == Some(&TokenKind::Or) {
+            self.position += 1;
+            exprs.push(self.and()?);
+        }
+        Ok(match exprs.len() {
+            1 => exprs.remove(0),
+            _ => Expr::Or(exprs),
+        })
+    }
+
+    fn and(&mut self) -> Result<Expr, QueryError> {
+        let mut exprs = vec![self.unary()?];
+        while !matches!(self.peek(), None | Some(TokenKind::Or | TokenKind::Close)) {
+            exprs.push(self.unary()?);
+        }
+        Ok(match exprs.len() {
+            1 => exprs.remove(0),
+            _ => Expr::And(exprs),
+        })
+    }
+
+    fn unary(&mut self) -> Result<Expr, QueryError> {
+        let column = self.column();
+        let Some(kind) = self.peek().cloned() else {
+            return Err(QueryError {
+                column,
+                message: "expected a term".to_string(),
+            });
+        };
+        self.position += 1;
+        match kind {
+            TokenKind::Not => self.nested(column, |parser| Ok(Expr::Not(Box::new(parser.unary()?)))),
+            TokenKind::Open => self.nested(column, |parser| {
+                let expr = parser.or()?;
+                if parser.peek() != Some(&TokenKind::Close) {
+                    return Err(QueryError {
+                        column,
+                        message: "( is never closed".to_string(),
+                    });
+                }
+                parser.position += 1;
+                Ok(expr)
+            }),
+            TokenKind::Quoted(text) => Ok(Expr::Text(text)),
+            TokenKind::Word(word) => self.term(&word, column),
+            TokenKind::Close | TokenKind::Or => Err(QueryError {
+                column,
+                message: "expected a term".to_string(),
+            }),
+        }
+    }
+
+    /// Parse one level deeper, refusing to go past `MAX_DEPTH`.
+    fn nested(
+        &mut self,
+        column: usize,
+        parse: impl FnOnce(&mut Parser) -> Result<Expr, QueryError>,
+    ) -> Result<Expr, QueryError> {
+        if self.depth == MAX_DEPTH {
+            return Err(QueryError {
+                column,
+                message: format!("nested more than {MAX_DEPTH} deep"),
+            });
+        }
+        self.depth += 1;
+        let expr = parse(self);
+        self.depth -= 1;
+        expr
+    }
+
+    /// A `field:value` or `field<value` term, a flag like `overdue`, or free text.
+    fn term(&self, word: &str, column: usize) -> Result<Expr, QueryError> {
+        let operator = OPERATORS
+            .iter()
+            .filter_map(|&(operator, comparison)| word.find(operator).map(|at| (at, operator, comparison)))
+            .min_by_key(|&(at, operator, _)| (at, std::cmp::Reverse(operator.len())));
+        let Some((at, operator, comparison)) = operator else {
+            let flag = FLAGS.iter().find(|(name, _)| name.eq_ignore_ascii_case(word));
+            return Ok(match flag {
+                Some(&(_, flag)) => Expr::Flag(flag),
+                None => Expr::Text(word.to_string()),
+            });
+        };
+
+        let field = word[..at].to_lowercase();
+        let value = &word[at + operator.len()..];
+        let value_column = column + word[..at + operator.len()].chars().count();
+        let error = |column, message: String| Err(QueryError { column, message });
+        if value.is_empty() {
+            return error(value_column, format!("{} needs a value", field));
+        }
+        let text_only = |expr: Expr| match comparison {
+            Comparison::Equal => Ok(expr),
+            _ => error(column, format!("{} only supports :", field)),
+        };
+        match field.as_str() {
+            "status" => text_only(Expr::Status(value.to_string())),
+            "tag" => text_only(Expr::Tag(value.trim_start_matches('#').to_lowercase())),
+            "title" => text_only(Expr::Title(value.to_string())),
+            "desc" | "description" => text_only(Expr::Description(value.to_string())),
+            "priority" => match value.parse() {
+                Ok(priority) => Ok(Expr::Priority(comparison, priority)),
+                Err(err) => error(value_column, err),
+            },
+            "due" | "created" | "updated" | "completed" => {
+                let field = match field.as_str() {
+                    "due" => DateField::Due,
+                    "created" => DateField::Created,
+                    "updated" => DateField::Updated,
+                    _ => DateField::Completed,
+                };
+                match dates::parse_due(value, self.today) {
+                    Ok(Some(due)) => Ok(Expr::Date(field, comparison, due.date)),
+                    Ok(None) => error(value_column, "expected a date".to_string()),
+                    Err(err) => error(value_column, err),
+                }
+            }
+            _ => error(
+                column,
+                format!(
+                    "unknown field {}, use status, tag, priority, title, desc, due, created, updated or completed",
+                    field
+                ),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    fn parse(text: &str) -> Expr {
+        Query::parse(text, date("2026-10-18")).unwrap().expr
+    }
+
+    /// Column and message of the error for `text`.
+    fn error(text: &str) -> (usize, String) {
+        let err = Query::parse(text, date("2026-10-18")).unwrap_err();
+        (err.column, err.message)
+    }
+
+    fn text(text: &str) -> Expr {
+        Expr::Text(text.to_string())
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(parse(""), Expr::All);
+        assert_eq!(parse("a b or c"), Expr::Or(vec![Expr::And(vec![text("a"), text("b")]), text("c")]));
+        assert_eq!(parse("a (b or c)"), Expr::And(vec![text("a"), Expr::Or(vec![text("b"), text("c")])]));
+        assert_eq!(parse("a OR b or c"), Expr::Or(vec![text("a"), text("b"), text("c")]));
+    }
+
+    #[test]
+    fn minus_negates_the_next_term() {
+        assert_eq!(
+            parse("-tag:#Work open"),
+            Expr::And(vec![Expr::Not(Box::new(Expr::Tag("work".to_string()))), Expr::Flag(Flag::Open)])
+        );
+        assert_eq!(parse("-(a or b)"), Expr::Not(Box::new(Expr::Or(vec![text("a"), text("b")]))));
+        assert_eq!(parse("--done"), Expr::Not(Box::new(Expr::Not(Box::new(Expr::Flag(Flag::Completed))))));
+    }
+
+    #[test]
+    fn quotes_keep_text_together() {
+        assert_eq!(parse("\"a or b\""), text("a or b"));
+        assert_eq!(parse("\"-done\""), text("-done"));
+        assert_eq!(parse("title:\"two words\""), Expr::Title("two words".to_string()));
+    }
+
+    #[test]
+    fn fields_take_comparisons() {
+        assert_eq!(parse("due<+7d"), Expr::Date(DateField::Due, Comparison::Less, date("2026-10-25")));
+        assert_eq!(parse("priority>=high"), Expr::Priority(Comparison::GreaterOrEqual, Priority::High));
+        assert_eq!(parse("Status:InProgress"), Expr::Status("InProgress".to_string()));
+    }
+
+    #[test]
+    fn errors_point_at_the_column() {
+        assert_eq!(error("status<x"), (1, "status only supports :".to_string()));
+        assert_eq!(error("a due:"), (7, "due needs a value".to_string()));
+        assert_eq!(error("é due:nope"), (7, "'nope' is not a date".to_string()));
+        assert_eq!(error("a \"open"), (3, "missing closing \"".to_string()));
+        assert_eq!(error("(a or b"), (1, "( is never closed".to_string()));
+        assert_eq!(error("a )"), (3, "unexpected )".to_string()));
+        assert_eq!(error("a or"), (5, "expected a term".to_string()));
+        assert_eq!(error("x foo:bar").0, 3);
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let deepest = format!("{}a{}", "(".repeat(MAX_DEPTH), ")".repeat(MAX_DEPTH));
+        assert_eq!(parse(&deepest), text("a"));
+        assert_eq!(error(&"-".repeat(MAX_DEPTH + 1)), (MAX_DEPTH + 1, "nested more than 64 deep".to_string()));
+        assert_eq!(error(&"(".repeat(100_000)).0, MAX_DEPTH + 1);
+    }
+}
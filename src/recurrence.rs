@@ -0,0 +1,305 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+/// How a todo repeats, written as an RRULE-like rule:
+///
+/// - `FREQ=DAILY;INTERVAL=2`, every other day
+/// - `FREQ=WEEKLY;BYDAY=MO,TH`, every Monday and Thursday
+/// - `FREQ=MONTHLY;BYMONTHDAY=15`, on the 15th (the last day in shorter months)
+/// - `FREQ=DAILY;INTERVAL=3;FROM=COMPLETION`, three days after it was last completed
+///
+/// `daily`, `weekly` and `monthly` are accepted as shorthands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    /// Every `interval` days, weeks or months
+    pub interval: u32,
+    /// Weekly rules only, empty repeats on the weekday of the last occurrence
+    pub weekdays: Vec<Weekday>,
+    /// Monthly rules only, `None` repeats on the day of the last occurrence,
+    /// see [`Recurrence::anchored`] for days that some months don't have
+    pub month_day: Option<u32>,
+    /// Count from the day the todo was completed instead of from its due date
+    pub from_completion: bool,
+}
+
+impl Recurrence {
+    /// The due date of the occurrence after one that was `due` and got completed `today`.
+    /// Rules that follow the due date skip occurrences that are already in the past.
+    /// `None` when the next occurrence would be past the last date chrono supports.
+    pub fn next(&self, due: Option<NaiveDate>, today: NaiveDate) -> Option<NaiveDate> {
+        let base = match due {
+            Some(due) if !self.from_completion => due,
+            _ => today,
+        };
+        let mut next = self.step(base)?;
+        while next < today {
+            next = self.step(next)?;
+        }
+        Some(next)
+    }
+
+    /// The rule to carry over to the occurrence after one that was `due`.
+    /// A monthly rule without a day that was due on the 29th or later keeps that day,
+    /// so the 31st moves to the 28th in February and back to the 31st in March.
+    pub fn anchored(mut self, due: Option<NaiveDate>) -> Recurrence {
+        if self.frequency == Frequency::Monthly
+            && self.month_day.is_none()
+            && !self.from_completion
+            && let Some(due) = due
+            && due.day() > 28
+        {
+            self.month_day = Some(due.day());
+        }
+        self
+    }
+
+    fn step(&self, from: NaiveDate) -> Option<NaiveDate> {
+        match self.frequency {
+            Frequency::Daily => from.checked_add_days(Days::new(self.interval.into())),
+            Frequency::Weekly if self.weekdays.is_empty() => {
+                from.checked_add_days(Days::new(7 * u64::from(self.interval)))
+            }
+            Frequency::Weekly => {
+                let week_start = from.checked_sub_days(Days::new(from.weekday().num_days_from_monday().into()))?;
+                let later_this_week = (from.weekday().num_days_from_monday() + 1..7)
+                    .filter_map(|offset| week_start.checked_add_days(Days::new(offset.into())))
+                    .find(|day| self.weekdays.contains(&day.weekday()));
+                if later_this_week.is_some() {
+                    return later_this_week;
+                }
+                let week = week_start.checked_add_days(Days::new(7 * u64::from(self.interval)))?;
+                (0..7)
+                    .filter_map(|offset| week.checked_add_days(Days::new(offset)))
+                    .find(|day| self.weekdays.contains(&day.weekday()))
+            }
+            Frequency::Monthly => {
+                let first = from.with_day(1).expect("every month has a first day");
+                let day = self.month_day.unwrap_or(from.day());
+                let this_month = day_of_month(first, day)?;
+                if self.month_day.is_some() && this_month > from {
+                    return Some(this_month);
+                }
+                day_of_month(first.checked_add_months(Months::new(self.interval))?, day)
+            }
+        }
+    }
+}
+
+/// `day` of the month starting at `first`, or its last day when the month is shorter.
+fn day_of_month(first: NaiveDate, day: u32) -> Option<NaiveDate> {
+    let last = match first.checked_add_months(Months::new(1)) {
+        Some(next_month) => (next_month - Days::new(1)).day(),
+        // Only the last month chrono supports has no month after it, it is a December
+        None => 31,
+    };
+    first.with_day(day.min(last))
+}
+
+/// Longer intervals are almost certainly typos, and would quickly run past the last date chrono supports.
+const MAX_INTERVAL: u32 = 1000;
+
+const WEEKDAYS: [(&str, Weekday); 7] = [
+    ("MO", Weekday::Mon),
+    ("TU", Weekday::Tue),
+    ("WE", Weekday::Wed),
+    ("TH", Weekday::Thu),
+    ("FR", Weekday::Fri),
+    ("SA", Weekday::Sat),
+    ("SU", Weekday::Sun),
+];
+
+fn parse_weekday(input: &str) -> Result<Weekday, String> {
+    WEEKDAYS
+        .iter()
+        .find(|(code, _)| code.eq_ignore_ascii_case(input))
+        .map(|(_, weekday)| *weekday)
+        .or_else(|| input.parse().ok())
+        .ok_or_else(|| format!("'{}' is not a weekday, use MO, TU, WE, TH, FR, SA or SU", input))
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        let rule = match input.to_lowercase().as_str() {
+            "daily" => "FREQ=DAILY".to_string(),
+            "weekly" => "FREQ=WEEKLY".to_string(),
+            "monthly" => "FREQ=MONTHLY".to_string(),
+            _ => input.to_string(),
+        };
+
+        let mut frequency = None;
+        let mut recurrence = Recurrence {
+            frequency: Frequency::Daily,
+            interval: 1,
+            weekdays: Vec::new(),
+            month_day: None,
+            from_completion: false,
+        };
+        for part in rule.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected KEY=VALUE, got '{}'", part))?;
+            match key.trim().to_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.trim().to_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        _ => return Err(format!("unknown FREQ '{}', use DAILY, WEEKLY or MONTHLY", value)),
+                    })
+                }
+                "INTERVAL" => {
+                    recurrence.interval = value
+                        .trim()
+                        .parse()
+                        .ok()
+                        .filter(|interval| (1..=MAX_INTERVAL).contains(interval))
+                        .ok_or_else(|| format!("INTERVAL has to be between 1 and {}, got '{}'", MAX_INTERVAL, value))?
+                }
+                "BYDAY" => {
+                    recurrence.weekdays = value
+                        .split(',')
+                        .map(|day| parse_weekday(day.trim()))
+                        .collect::<Result<_, _>>()?
+                }
+                "BYMONTHDAY" => {
+                    recurrence.month_day = Some(
+                        value
+                            .trim()
+                            .parse()
+                            .ok()
+                            .filter(|day| (1..=31).contains(day))
+                            .ok_or_else(|| format!("BYMONTHDAY has to be between 1 and 31, got '{}'", value))?,
+                    )
+                }
+                "FROM" => {
+                    recurrence.from_completion = match value.trim().to_uppercase().as_str() {
+                        "COMPLETION" => true,
+                        "DUE" => false,
+                        _ => return Err(format!("unknown FROM '{}', use DUE or COMPLETION", value)),
+                    }
+                }
+                _ => return Err(format!("unknown rule part '{}'", key)),
+            }
+        }
+
+        recurrence.frequency = frequency.ok_or("the rule needs a FREQ")?;
+        if !recurrence.weekdays.is_empty() && recurrence.frequency != Frequency::Weekly {
+            return Err("BYDAY only works with FREQ=WEEKLY".to_string());
+        }
+        if recurrence.month_day.is_some() && recurrence.frequency != Frequency::Monthly {
+            return Err("BYMONTHDAY only works with FREQ=MONTHLY".to_string());
+        }
+        Ok(recurrence)
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let frequency = match self.frequency {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+        };
+        write!(f, "FREQ={}", frequency)?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.weekdays.is_empty() {
+            let days: Vec<_> = self
+                .weekdays
+                .iter()
+                .filter_map(|weekday| WEEKDAYS.iter().find(|(_, day)| day == weekday))
+                .map(|(code, _)| *code)
+                .collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if let Some(day) = self.month_day {
+            write!(f, ";BYMONTHDAY={}", day)?;
+        }
+        if self.from_completion {
+            write!(f, ";FROM=COMPLETION")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn interval_is_bounded() {
+        assert!("FREQ=DAILY;INTERVAL=1000".parse::<Recurrence>().is_ok());
+        assert!("FREQ=DAILY;INTERVAL=1001".parse::<Recurrence>().is_err());
+        assert!("FREQ=DAILY;INTERVAL=4000000000".parse::<Recurrence>().is_err());
+        assert!("FREQ=DAILY;INTERVAL=0".parse::<Recurrence>().is_err());
+    }
+
+    #[test]
+    fn no_next_occurrence_past_the_last_date() {
+        let last = NaiveDate::MAX;
+        for rule in ["FREQ=DAILY", "FREQ=WEEKLY", "FREQ=WEEKLY;BYDAY=MO", "FREQ=MONTHLY;INTERVAL=1000"] {
+            let recurrence: Recurrence = rule.parse().unwrap();
+            assert_eq!(recurrence.next(Some(last), last), None, "{}", rule);
+        }
+        let monthly: Recurrence = "FREQ=MONTHLY;INTERVAL=1000".parse().unwrap();
+        assert_eq!(monthly.next(Some(date("2026-01-31")), date("2026-01-31")), Some(date("2109-05-31")));
+    }
+
+    /// The occurrence after one due on `due` and completed on `today`.
+    fn next(rule: &str, due: &str, today: &str) -> NaiveDate {
+        let recurrence: Recurrence = rule.parse().unwrap();
+        recurrence.next(Some(date(due)), date(today)).unwrap()
+    }
+
+    #[test]
+    fn occurrences_cross_month_ends() {
+        assert_eq!(next("FREQ=DAILY;INTERVAL=3", "2026-01-30", "2026-01-30"), date("2026-02-02"));
+        assert_eq!(next("FREQ=WEEKLY;BYDAY=MO,TH", "2026-10-30", "2026-10-30"), date("2026-11-02"));
+        assert_eq!(next("monthly", "2026-01-31", "2026-01-31"), date("2026-02-28"));
+        assert_eq!(next("FREQ=MONTHLY;BYMONTHDAY=31", "2026-01-31", "2026-01-31"), date("2026-02-28"));
+        assert_eq!(next("FREQ=MONTHLY;BYMONTHDAY=31", "2026-02-28", "2026-02-28"), date("2026-03-31"));
+        assert_eq!(next("FREQ=MONTHLY;BYMONTHDAY=29", "2028-01-29", "2028-01-29"), date("2028-02-29"));
+        assert_eq!(next("FREQ=MONTHLY;BYMONTHDAY=15", "2026-11-15", "2026-11-20"), date("2026-12-15"));
+        assert_eq!(next("FREQ=MONTHLY;BYMONTHDAY=15", "2026-12-15", "2026-12-15"), date("2027-01-15"));
+    }
+
+    #[test]
+    fn monthly_rules_keep_the_day_they_started_on() {
+        let mut due = date("2026-01-31");
+        let mut recurrence: Recurrence = "monthly".parse().unwrap();
+        let mut dues = Vec::new();
+        for _ in 0..3 {
+            recurrence = recurrence.anchored(Some(due));
+            due = recurrence.next(Some(due), due).unwrap();
+            dues.push(due);
+        }
+        assert_eq!(dues, [date("2026-02-28"), date("2026-03-31"), date("2026-04-30")]);
+        assert_eq!(recurrence.to_string(), "FREQ=MONTHLY;BYMONTHDAY=31");
+
+        let mid_month: Recurrence = "monthly".parse().unwrap();
+        assert_eq!(mid_month.clone().anchored(Some(date("2026-01-15"))), mid_month);
+    }
+
+    #[test]
+    fn past_occurrences_are_skipped() {
+        assert_eq!(next("monthly", "2026-01-15", "2026-04-20"), date("2026-05-15"));
+        assert_eq!(next("FREQ=DAILY;INTERVAL=3;FROM=COMPLETION", "2026-01-01", "2026-01-31"), date("2026-02-03"));
+    }
+}
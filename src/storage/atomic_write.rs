@@ -0,0 +1,46 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::Path,
+};
+
+use crate::storage::StorageError;
+
+/// Replace `path` with `data` without ever leaving a half written file behind.
+///
+/// The data goes to a temporary file in the same directory, is flushed to disk
+/// and then renamed over the original, so a crash or a full disk leaves either
+/// the old or the new contents in place.
+pub fn write_atomic(path: &Path, data: &[u8]) -> Result<(), StorageError> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp_path = dir.join(format!(".{}.tmp-{}", file_name, std::process::id()));
+
+    let result = File::create(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(data)?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&tmp_path, path));
+
+    if let Err(err) = result {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(StorageError::io(path, err));
+    }
+
+    sync_dir(dir);
+    Ok(())
+}
+
+/// Make the rename itself durable. Best effort, not every platform can open directories.
+fn sync_dir(dir: &Path) {
+    #[cfg(unix)]
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    #[cfg(not(unix))]
+    let _ = dir;
+}
@@ -0,0 +1,166 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::Local;
+
+use crate::storage::StorageError;
+
+const BACKUP_DIR: &str = "backups";
+
+/// Rotating, timestamped copies of the data file.
+///
+/// Backups of `todo/data.json` live in `todo/backups/` and are named
+/// `data-<YYYYMMDD-HHMMSS-mmm>.json`, so sorting them by name sorts them by age.
+#[derive(Debug, Default)]
+pub struct Backups {
+    dir: PathBuf,
+    stem: String,
+    extension: String,
+    keep: usize,
+}
+
+impl Backups {
+    pub fn new(data_file: &Path, keep: usize) -> Self {
+        let dir = data_file.parent().unwrap_or(Path::new("")).join(BACKUP_DIR);
+        let stem = data_file.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        let extension = data_file.extension().unwrap_or_default().to_string_lossy().into_owned();
+        Self {
+            dir,
+            stem,
+            extension,
+            keep,
+        }
+    }
+
+    /// Copy `data_file` into a new backup and drop the oldest ones beyond the configured count.
+    pub fn create(&self, data_file: &Path) -> Result<Option<PathBuf>, StorageError> {
+        if self.keep == 0 || !data_file.exists() {
+            return Ok(None);
+        }
+
+        fs::create_dir_all(&self.dir).map_err(|err| StorageError::io(&self.dir, err))?;
+        let timestamp = Local::now().format("%Y%m%d-%H%M%S-%3f");
+        let backup = self.dir.join(self.file_name(&timestamp.to_string()));
+        fs::copy(data_file, &backup).map_err(|err| StorageError::io(&backup, err))?;
+
+        for old in self.list()?.into_iter().skip(self.keep) {
+            fs::remove_file(&old).map_err(|err| StorageError::io(old, err))?;
+        }
+        Ok(Some(backup))
+    }
+
+    /// All backups, newest first.
+    pub fn list(&self) -> Result<Vec<PathBuf>, StorageError> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let entries = fs::read_dir(&self.dir).map_err(|err| StorageError::io(&self.dir, err))?;
+        let mut backups: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| self.is_backup(&path.file_name().unwrap_or_default().to_string_lossy()))
+            .collect();
+        backups.sort();
+        backups.reverse();
+        Ok(backups)
+    }
+
+    /// Look a backup up by position (`1` is the newest), by file name or as `latest`.
+    pub fn find(&self, name: &str) -> Result<PathBuf, StorageError> {
+        let backups = self.list()?;
+        let found = match name.parse::<usize>() {
+            Ok(position) if position > 0 => backups.get(position - 1).cloned(),
+            _ if name == "latest" => backups.first().cloned(),
+            _ => backups
+                .into_iter()
+                .find(|path| path.file_name().is_some_and(|file_name| file_name == name)),
+        };
+        found.ok_or_else(|| StorageError::BackupNotFound(name.to_string()))
+    }
+
+    /// Move an unreadable data file out of the way, keeping it next to the original
+    /// with an extra `.corrupt` extension.
+    pub fn set_aside_corrupt(data_file: &Path) -> Result<(), StorageError> {
+        if !data_file.exists() {
+            return Ok(());
+        }
+        let mut corrupt = data_file.as_os_str().to_owned();
+        corrupt.push(".corrupt");
+        fs::rename(data_file, &corrupt).map_err(|err| StorageError::io(corrupt, err))
+    }
+
+    /// Whether `name` is exactly what [`Backups::file_name`] makes. Lists share the backup
+    /// directory, so `work-old-<timestamp>.json` must not count as a backup of `work`.
+    fn is_backup(&self, name: &str) -> bool {
+        let Some(rest) = name.strip_prefix(&self.stem).and_then(|rest| rest.strip_prefix('-')) else {
+            return false;
+        };
+        let timestamp = match self.extension.is_empty() {
+            true => Some(rest),
+            false => rest
+                .strip_suffix(self.extension.as_str())
+                .and_then(|rest| rest.strip_suffix('.')),
+        };
+        // YYYYMMDD-HHMMSS-mmm
+        timestamp.is_some_and(|timestamp| {
+            timestamp.len() == 19
+                && timestamp.char_indices().all(|(position, c)| match position {
+                    8 | 15 => c == '-',
+                    _ => c.is_ascii_digit(),
+                })
+        })
+    }
+
+    fn file_name(&self, timestamp: &str) -> String {
+        if self.extension.is_empty() {
+            format!("{}-{}", self.stem, timestamp)
+        } else {
+            format!("{}-{}.{}", self.stem, timestamp, self.extension)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("todo-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn lists_sharing_a_prefix_keep_their_own_backups() {
+        let dir = temp_dir("backups-prefix");
+        let work = dir.join("work.json");
+        let work_old = dir.join("work-old.json");
+        fs::write(&work, "work").unwrap();
+        fs::write(&work_old, "work-old").unwrap();
+
+        let work_backups = Backups::new(&work, 1);
+        let work_old_backups = Backups::new(&work_old, 1);
+        work_backups.create(&work).unwrap();
+        work_old_backups.create(&work_old).unwrap();
+        // Rotation of one list leaves the backups of the other alone
+        work_old_backups.create(&work_old).unwrap();
+
+        let latest = work_backups.find("latest").unwrap();
+        assert_eq!(fs::read_to_string(latest).unwrap(), "work");
+        assert_eq!(work_backups.list().unwrap().len(), 1);
+        assert_eq!(work_old_backups.list().unwrap().len(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn only_timestamped_names_are_backups() {
+        let backups = Backups::new(Path::new("todo/work.json"), 5);
+        assert!(backups.is_backup("work-20261018-120501-042.json"));
+        assert!(!backups.is_backup("work-old-20261018-120501-042.json"));
+        assert!(!backups.is_backup("work-20261018-120501-042.db"));
+        assert!(!backups.is_backup("work-20261018.json"));
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    storage::{Backups, StorageError, StoreBatch, TodoDocument, TodoQuery, TodoStore, write_atomic},
+    todo::TodoItem,
+};
+
+/// Stores the whole list as a versioned [`TodoDocument`] in a single JSON file.
+#[derive(Debug)]
+pub struct JsonStore {
+    path: PathBuf,
+    backups: Backups,
+    document: TodoDocument,
+    backed_up: bool,
+}
+
+impl JsonStore {
+    pub fn new(path: PathBuf, backups: Backups) -> Self {
+        Self {
+            path,
+            backups,
+            document: TodoDocument::default(),
+            backed_up: false,
+        }
+    }
+
+    fn write(&mut self) -> Result<(), StorageError> {
+        self.document.set_current_version();
+        let data = serde_json::to_string(&self.document).map_err(StorageError::Serialize)?;
+        // Only the file as it was before this session is worth a backup
+        if !self.backed_up {
+            self.backups.create(&self.path)?;
+            self.backed_up = true;
+        }
+        write_atomic(&self.path, data.as_bytes())
+    }
+}
+
+impl TodoStore for JsonStore {
+    fn load(&mut self) -> Result<Vec<TodoItem>, StorageError> {
+        if !self.path.exists() {
+            // First run, create the data file so later saves have somewhere to go
+            if let Some(dir) = self.path.parent() {
+                fs::create_dir_all(dir).map_err(|err| StorageError::io(dir, err))?;
+            }
+            fs::write(&self.path, "").map_err(|err| StorageError::io(&self.path, err))?;
+        }
+
+        self.document = read_file(&self.path)?;
+        Ok(self.document.items.clone())
+    }
+
+    fn write_batch(&mut self, batch: &StoreBatch) -> Result<(), StorageError> {
+        let items = &mut self.document.items;
+        items.retain(|item| !batch.delete.contains(&item.id));
+        for item in &batch.update {
+            if let Some(stored) = items.iter_mut().find(|stored| stored.id == item.id) {
+                *stored = item.clone();
+            }
+        }
+        items.extend(batch.insert.iter().cloned());
+        self.write()
+    }
+
+    fn query(&mut self, query: &TodoQuery) -> Result<Vec<TodoItem>, StorageError> {
+        Ok(self
+            .document
+            .items
+            .iter()
+            .filter(|item| query.matches(item))
+            .cloned()
+            .collect())
+    }
+
+    fn save_all(&mut self, items: &[TodoItem]) -> Result<(), StorageError> {
+        self.document.items = items.to_vec();
+        self.write()
+    }
+
+    fn list_backups(&self) -> Result<Vec<PathBuf>, StorageError> {
+        self.backups.list()
+    }
+
+    fn restore_backup(&mut self, name: &str) -> Result<PathBuf, StorageError> {
+        let backup = self.backups.find(name)?;
+        read_file(&backup)?;
+        let data = fs::read(&backup).map_err(|err| StorageError::io(&backup, err))?;
+        // Back up the current file first so the restore can itself be undone
+        self.backups.create(&self.path)?;
+        write_atomic(&self.path, &data)?;
+        Ok(backup)
+    }
+
+    fn recover_from_backup(&mut self) -> Result<(), StorageError> {
+        let mut last_error = StorageError::BackupNotFound("latest".to_string());
+        for backup in self.backups.list()? {
+            if let Err(err) = read_file(&backup) {
+                last_error = err;
+                continue;
+            }
+            let data = fs::read(&backup).map_err(|err| StorageError::io(&backup, err))?;
+            Backups::set_aside_corrupt(&self.path)?;
+            return write_atomic(&self.path, &data);
+        }
+        Err(last_error)
+    }
+
+    fn start_empty(&mut self) -> Result<(), StorageError> {
+        Backups::set_aside_corrupt(&self.path)
+    }
+}
+
+fn read_file(path: &Path) -> Result<TodoDocument, StorageError> {
+    let data = fs::read_to_string(path).map_err(|err| StorageError::io(path, err))?;
+    TodoDocument::parse(&data, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::todo::TodoStatus;
+
+    fn item(title: &str) -> TodoItem {
+        TodoItem::new_todo_item(title.to_string(), String::new(), TodoStatus::default())
+    }
+
+    #[test]
+    fn a_batch_deletes_updates_and_appends() {
+        let dir = std::env::temp_dir().join(format!("todo-json-batch-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("data.json");
+        let mut store = JsonStore::new(path.clone(), Backups::new(&path, 0));
+        store.load().unwrap();
+        let (a, mut b, c) = (item("a"), item("b"), item("c"));
+        for item in [&a, &b, &c] {
+            store.insert(item).unwrap();
+        }
+
+        b.title = "b2".to_string();
+        let d = item("d");
+        let batch = StoreBatch {
+            insert: vec![d.clone()],
+            update: vec![b.clone()],
+            delete: vec![a.id],
+        };
+        store.write_batch(&batch).unwrap();
+
+        let mut reopened = JsonStore::new(path.clone(), Backups::new(&path, 0));
+        assert_eq!(reopened.load().unwrap(), vec![b, c, d]);
+    }
+}
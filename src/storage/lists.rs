@@ -0,0 +1,103 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::storage::{StorageError, StoreBackend, TodoStore, open_store};
+
+const LISTS_DIR: &str = "lists";
+const HISTORY_EXTENSION: &str = "history";
+
+/// Where the named todo lists are stored. The default list is the data file itself,
+/// every other list is a file of the same kind in a `lists` directory next to it.
+#[derive(Debug, Clone)]
+pub struct ListLocator {
+    data_file: PathBuf,
+    backend: StoreBackend,
+    backup_count: usize,
+}
+
+impl ListLocator {
+    pub const DEFAULT: &str = "default";
+
+    pub fn new(data_file: PathBuf, backend: StoreBackend, backup_count: usize) -> Self {
+        Self {
+            data_file,
+            backend,
+            backup_count,
+        }
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        if name == Self::DEFAULT {
+            return self.data_file.clone();
+        }
+        self.lists_dir()
+            .join(name)
+            .with_extension(self.backend.extension())
+    }
+
+    pub fn open(&self, name: &str) -> Box<dyn TodoStore> {
+        open_store(self.backend, self.path(name), self.backup_count)
+    }
+
+    /// The default list first, then every list found on disk by name.
+    pub fn names(&self) -> Result<Vec<String>, StorageError> {
+        let dir = self.lists_dir();
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Ok(vec![Self::DEFAULT.to_string()]);
+            }
+            Err(err) => return Err(StorageError::io(&dir, err)),
+        };
+
+        let mut names = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(|err| StorageError::io(&dir, err))?.path();
+            if path
+                .extension()
+                .is_none_or(|extension| extension != self.backend.extension())
+            {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|stem| stem.to_str())
+                && validate_name(name).is_ok()
+                && name != Self::DEFAULT
+            {
+                names.push(name.to_string());
+            }
+        }
+        names.sort();
+        names.insert(0, Self::DEFAULT.to_string());
+        Ok(names)
+    }
+
+    /// The undo history of every list, see [`History`](crate::history::History).
+    /// It sits next to the data file, with an extension no list uses.
+    pub fn history_path(&self) -> PathBuf {
+        self.data_file.with_extension(HISTORY_EXTENSION)
+    }
+
+    fn lists_dir(&self) -> PathBuf {
+        self.data_file
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join(LISTS_DIR)
+    }
+}
+
+/// List names end up as file names, so they are limited to letters, digits, `-` and `_`.
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    match valid {
+        true => Ok(()),
+        false => Err(format!(
+            "'{}' is not a valid list name, use letters, digits, - and _",
+            name
+        )),
+    }
+}
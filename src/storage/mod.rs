@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+pub mod atomic_write;
+pub mod backups;
+pub mod json_store;
+pub mod lists;
+pub mod schema;
+pub mod sqlite_store;
+pub mod storage_error;
+pub mod todo_store;
+
+pub use atomic_write::write_atomic;
+pub use backups::Backups;
+pub use json_store::JsonStore;
+pub use lists::ListLocator;
+pub use schema::TodoDocument;
+pub use sqlite_store::SqliteStore;
+pub use storage_error::StorageError;
+pub use todo_store::{StoreBatch, TodoQuery, TodoStore};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreBackend {
+    #[default]
+    Json,
+    Sqlite,
+}
+
+impl StoreBackend {
+    pub fn default_file_name(&self) -> &'static str {
+        match self {
+            StoreBackend::Json => "data.json",
+            StoreBackend::Sqlite => "data.db",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            StoreBackend::Json => "json",
+            StoreBackend::Sqlite => "db",
+        }
+    }
+}
+
+pub fn open_store(backend: StoreBackend, path: PathBuf, backup_count: usize) -> Box<dyn TodoStore> {
+    let backups = Backups::new(&path, backup_count);
+    match backend {
+        StoreBackend::Json => Box::new(JsonStore::new(path, backups)),
+        StoreBackend::Sqlite => Box::new(SqliteStore::new(path, backups)),
+    }
+}
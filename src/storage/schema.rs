@@ -0,0 +1,186 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use chrono::Utc;
+use serde_json::{Map, Value, json};
+
+use crate::{storage::StorageError, todo::TodoItem, workflow::workflow};
+
+/// Version of the document format written by this build.
+pub const SCHEMA_VERSION: u64 = 3;
+
+/// `MIGRATIONS[n]` upgrades a version `n` document to version `n + 1`.
+const MIGRATIONS: &[fn(Value) -> Value] = &[wrap_bare_array, add_timestamps, status_only_lifecycle];
+
+/// On-disk format of the todo data:
+/// `{ "schema_version": 3, "items": [ ... ] }`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TodoDocument {
+    pub schema_version: u64,
+    #[serde(default)]
+    pub items: Vec<TodoItem>,
+    /// Fields written by a newer build, kept so saving does not drop them
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Default for TodoDocument {
+    fn default() -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            items: Vec::new(),
+            extra: Map::new(),
+        }
+    }
+}
+
+impl TodoDocument {
+    /// Parse a data file of any known version, upgrading it to the current one.
+    /// Documents from newer builds are read as far as this build understands them.
+    pub fn parse(data: &str, path: &Path) -> Result<TodoDocument, StorageError> {
+        if data.trim().is_empty() {
+            return Ok(TodoDocument::default());
+        }
+        let malformed = |source| StorageError::Malformed {
+            path: path.to_path_buf(),
+            source,
+        };
+        let value: Value = serde_json::from_str(data).map_err(malformed)?;
+        serde_json::from_value(upgrade(value, path)?).map_err(malformed)
+    }
+
+    /// Newer builds never get their version marker downgraded by an older one.
+    pub fn set_current_version(&mut self) {
+        self.schema_version = self.schema_version.max(SCHEMA_VERSION);
+    }
+}
+
+/// Run every migration between the version of `value` and [`SCHEMA_VERSION`].
+pub fn upgrade(mut value: Value, path: &Path) -> Result<Value, StorageError> {
+    let version = document_version(&value).ok_or_else(|| StorageError::Schema {
+        path: path.to_path_buf(),
+        message: "expected a todo array or a document with a schema_version".to_string(),
+    })?;
+    for migration in MIGRATIONS.iter().skip(version as usize) {
+        value = migration(value);
+    }
+    Ok(value)
+}
+
+fn document_version(value: &Value) -> Option<u64> {
+    match value {
+        // Before versioning the file was a bare array of items
+        Value::Array(_) => Some(0),
+        Value::Object(document) => document.get("schema_version")?.as_u64(),
+        _ => None,
+    }
+}
+
+/// 0 -> 1: the bare item array becomes the `items` of a versioned document.
+fn wrap_bare_array(value: Value) -> Value {
+    json!({ "schema_version": 1, "items": value })
+}
+
+/// 1 -> 2: items get `created_at` and `updated_at`. The real creation time is unknown,
+/// so both are set to the time of the upgrade; `completed_at` stays unknown.
+fn add_timestamps(mut value: Value) -> Value {
+    let now = json!(Utc::now());
+    if let Some(items) = value["items"].as_array_mut() {
+        for item in items.iter_mut().filter_map(Value::as_object_mut) {
+            item.entry("created_at").or_insert_with(|| now.clone());
+            item.entry("updated_at").or_insert_with(|| now.clone());
+        }
+    }
+    value["schema_version"] = json!(2);
+    value
+}
+
+/// 2 -> 3: the `completed` flag is folded into `status`. Where the two disagreed the
+/// item counts as completed if either of them said so, with the done status of the workflow.
+fn status_only_lifecycle(value: Value) -> Value {
+    fold_completed(value, workflow().done_status().name())
+}
+
+fn fold_completed(mut value: Value, done: &str) -> Value {
+    if let Some(items) = value["items"].as_array_mut() {
+        for item in items.iter_mut().filter_map(Value::as_object_mut) {
+            if item.remove("completed") == Some(Value::Bool(true)) {
+                item.insert("status".to_string(), json!(done));
+            }
+        }
+    }
+    value["schema_version"] = json!(3);
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::StatusConfig, workflow::Workflow};
+
+    fn parse(data: &str) -> TodoDocument {
+        TodoDocument::parse(data, Path::new("data.json")).unwrap()
+    }
+
+    #[test]
+    fn a_bare_array_is_upgraded_to_the_current_version() {
+        let document = parse(r#"[{"title": "done", "completed": true}, {"title": "open", "status": "InProgress"}]"#);
+        assert_eq!(document.schema_version, SCHEMA_VERSION);
+        assert_eq!(document.items[0].status.name(), "Completed");
+        assert_eq!(document.items[1].status.name(), "InProgress");
+        assert!(document.items.iter().all(|item| item.completed_at.is_none()));
+    }
+
+    #[test]
+    fn newer_documents_keep_their_version_and_fields() {
+        let mut document = parse(r#"{"schema_version": 9, "items": [], "labels": ["x"]}"#);
+        document.set_current_version();
+        assert_eq!(document.schema_version, 9);
+        assert_eq!(document.extra["labels"], json!(["x"]));
+    }
+
+    #[test]
+    fn unknown_layouts_are_refused() {
+        assert!(matches!(
+            TodoDocument::parse(r#""todos""#, Path::new("data.json")),
+            Err(StorageError::Schema { .. })
+        ));
+        assert_eq!(parse("  \n").schema_version, SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn timestamps_are_added_once() {
+        let created = "2020-01-02T03:04:05Z";
+        let value = json!({ "schema_version": 1, "items": [{ "title": "a", "created_at": created }, { "title": "b" }] });
+        let value = upgrade(value, Path::new("data.json")).unwrap();
+        assert_eq!(value["items"][0]["created_at"], json!(created));
+        assert!(value["items"][0]["updated_at"].is_string());
+        assert!(value["items"][1]["created_at"].is_string());
+    }
+
+    #[test]
+    fn the_completed_flag_is_folded_into_the_status() {
+        let items = json!([
+            { "title": "a", "status": "Pending", "completed": true },
+            { "title": "b", "status": "InProgress", "completed": false },
+        ]);
+        let value = upgrade(json!({ "schema_version": 2, "items": items }), Path::new("data.json")).unwrap();
+        assert_eq!(value["schema_version"], json!(3));
+        assert_eq!(value["items"][0]["status"], json!("Completed"));
+        assert_eq!(value["items"][1]["status"], json!("InProgress"));
+        assert!(value["items"][0].get("completed").is_none());
+    }
+
+    #[test]
+    fn completed_items_get_the_done_status_of_the_workflow() {
+        let statuses = [("Todo", false), ("Done", true), ("Archived", true)].map(|(name, done)| StatusConfig {
+            name: name.to_string(),
+            color: None,
+            done,
+        });
+        let workflow = Workflow::from_config(&statuses).unwrap();
+        let items = json!([{ "title": "a", "status": "Todo", "completed": true }]);
+        let value = fold_completed(json!({ "schema_version": 2, "items": items }), workflow.done_status().name());
+        assert_eq!(value["items"][0]["status"], json!("Done"));
+    }
+}
@@ -0,0 +1,477 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use rusqlite::{Connection, OpenFlags, params, params_from_iter, types::Value as SqlValue};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::{
+    form::form_inputs::enum_field::EnumDisplay,
+    storage::{
+        Backups, StorageError, StoreBatch, TodoDocument, TodoQuery, TodoStore, write_atomic,
+        schema::{self, SCHEMA_VERSION},
+    },
+    todo::TodoItem,
+    workflow::workflow,
+};
+
+// The full item is kept as JSON in `data`, the other columns only exist so they can be indexed and queried.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS todos (
+        id TEXT PRIMARY KEY NOT NULL,
+        position INTEGER NOT NULL,
+        title TEXT NOT NULL,
+        description TEXT NOT NULL,
+        status TEXT NOT NULL,
+        completed INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS todos_position ON todos (position);
+    CREATE INDEX IF NOT EXISTS todos_status ON todos (status);
+    CREATE INDEX IF NOT EXISTS todos_completed ON todos (completed);
+";
+
+/// Stores every item as its own row in an embedded SQLite database.
+#[derive(Debug)]
+pub struct SqliteStore {
+    path: PathBuf,
+    backups: Backups,
+    connection: Option<Connection>,
+    backed_up: bool,
+}
+
+impl SqliteStore {
+    pub fn new(path: PathBuf, backups: Backups) -> Self {
+        Self {
+            path,
+            backups,
+            connection: None,
+            backed_up: false,
+        }
+    }
+
+    fn connection(&mut self) -> Result<&mut Connection, StorageError> {
+        if self.connection.is_none() {
+            if let Some(dir) = self.path.parent() {
+                fs::create_dir_all(dir).map_err(|err| StorageError::io(dir, err))?;
+            }
+            let mut connection = open(&self.path)?;
+            if migrate(&mut connection, &self.path, &self.backups)? {
+                self.backed_up = true;
+            }
+            self.connection = Some(connection);
+        }
+        Ok(self
+            .connection
+            .as_mut()
+            .expect("connection was just opened"))
+    }
+
+    /// Connection for a write, backing up the database as it was before this session changed it.
+    fn write_connection(&mut self) -> Result<&mut Connection, StorageError> {
+        if !self.backed_up {
+            // No transaction is open between writes, so the file on disk is consistent
+            self.backups.create(&self.path)?;
+            self.backed_up = true;
+        }
+        self.connection()
+    }
+
+    /// Swap the database file for `data` (or remove it), reopening lazily on the next access.
+    fn replace_file(&mut self, data: Option<&[u8]>) -> Result<(), StorageError> {
+        self.connection = None;
+        Backups::set_aside_corrupt(&self.path)?;
+        if let Some(data) = data {
+            write_atomic(&self.path, data)?;
+        }
+        Ok(())
+    }
+
+    fn db_error(&self, source: rusqlite::Error) -> StorageError {
+        StorageError::Database {
+            path: self.path.clone(),
+            source,
+        }
+    }
+}
+
+impl TodoStore for SqliteStore {
+    fn load(&mut self) -> Result<Vec<TodoItem>, StorageError> {
+        let path = self.path.clone();
+        let connection = self.connection()?;
+        read_items(connection, &path)
+    }
+
+    fn write_batch(&mut self, batch: &StoreBatch) -> Result<(), StorageError> {
+        let result = apply_batch(self.write_connection()?, batch);
+        result.map_err(|err| self.db_error(err))
+    }
+
+    fn query(&mut self, query: &TodoQuery) -> Result<Vec<TodoItem>, StorageError> {
+        let mut conditions = vec!["1 = 1".to_string()];
+        let mut values = Vec::new();
+        if let Some(status) = &query.status {
+            values.push(SqlValue::Text(status.to_str().to_string()));
+            conditions.push(format!("status = ?{} COLLATE NOCASE", values.len()));
+        }
+        // Which statuses are done depends on the current workflow, not on the stored column
+        if let Some(completed) = query.completed {
+            let mut placeholders = Vec::new();
+            for status in workflow().statuses().iter().filter(|status| status.is_completed()) {
+                values.push(SqlValue::Text(status.name().to_string()));
+                placeholders.push(format!("?{}", values.len()));
+            }
+            let negate = if completed { "" } else { "NOT " };
+            conditions.push(format!("status COLLATE NOCASE {}IN ({})", negate, placeholders.join(", ")));
+        }
+        for tag in &query.tags {
+            values.push(SqlValue::Text(tag.clone()));
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM json_each(data, '$.tags') WHERE value = ?{})",
+                values.len()
+            ));
+        }
+        if let Some(text) = &query.text {
+            let escaped = text
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            values.push(SqlValue::Text(format!("%{}%", escaped)));
+            let n = values.len();
+            conditions.push(format!(
+                "(title LIKE ?{n} ESCAPE '\\' OR description LIKE ?{n} ESCAPE '\\')"
+            ));
+        }
+        let sql = format!(
+            "SELECT data FROM todos WHERE {} ORDER BY position",
+            conditions.join(" AND ")
+        );
+
+        let path = self.path.clone();
+        let connection = self.connection()?;
+        let rows = connection
+            .prepare(&sql)
+            .and_then(|mut statement| {
+                statement
+                    .query_map(params_from_iter(values), |row| row.get::<_, String>(0))?
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .map_err(|source| StorageError::Database {
+                path: path.clone(),
+                source,
+            })?;
+        rows.iter().map(|data| parse_item(data, &path)).collect()
+    }
+
+    fn save_all(&mut self, items: &[TodoItem]) -> Result<(), StorageError> {
+        let path = self.path.clone();
+        let connection = self.write_connection()?;
+        write_items(connection, items).map_err(|source| StorageError::Database { path, source })
+    }
+
+    fn list_backups(&self) -> Result<Vec<PathBuf>, StorageError> {
+        self.backups.list()
+    }
+
+    fn restore_backup(&mut self, name: &str) -> Result<PathBuf, StorageError> {
+        let backup = self.backups.find(name)?;
+        check_backup(&backup)?;
+        let data = fs::read(&backup).map_err(|err| StorageError::io(&backup, err))?;
+        self.connection = None;
+        // Back up the current database first so the restore can itself be undone
+        self.backups.create(&self.path)?;
+        write_atomic(&self.path, &data)?;
+        Ok(backup)
+    }
+
+    fn recover_from_backup(&mut self) -> Result<(), StorageError> {
+        let mut last_error = StorageError::BackupNotFound("latest".to_string());
+        for backup in self.backups.list()? {
+            if let Err(err) = check_backup(&backup) {
+                last_error = err;
+                continue;
+            }
+            let data = fs::read(&backup).map_err(|err| StorageError::io(&backup, err))?;
+            return self.replace_file(Some(&data));
+        }
+        Err(last_error)
+    }
+
+    fn start_empty(&mut self) -> Result<(), StorageError> {
+        self.replace_file(None)
+    }
+}
+
+fn open(path: &Path) -> Result<Connection, StorageError> {
+    let database_error = |source| StorageError::Database {
+        path: path.to_path_buf(),
+        source,
+    };
+    let connection = Connection::open(path).map_err(database_error)?;
+    connection.execute_batch(SCHEMA).map_err(database_error)?;
+    Ok(connection)
+}
+
+/// Make sure a backup can be read and migrated, without changing the file.
+/// Only the restored copy is migrated, once it is opened as the database.
+fn check_backup(path: &Path) -> Result<(), StorageError> {
+    let database_error = |source| StorageError::Database {
+        path: path.to_path_buf(),
+        source,
+    };
+    let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(database_error)?;
+    let version = stored_version(&connection).map_err(database_error)?;
+    upgraded_items(&connection, path, version.max(1)).map(drop)
+}
+
+fn read_items(connection: &Connection, path: &Path) -> Result<Vec<TodoItem>, StorageError> {
+    let rows = read_rows(connection).map_err(|source| StorageError::Database {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    rows.iter().map(|data| parse_item(data, path)).collect()
+}
+
+fn read_rows(connection: &Connection) -> rusqlite::Result<Vec<String>> {
+    connection
+        .prepare("SELECT data FROM todos ORDER BY position")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect()
+}
+
+/// Bring the rows up to [`SCHEMA_VERSION`], which SQLite tracks as the database `user_version`.
+/// The rows go through the same migrations as the JSON file, wrapped in a document.
+/// Returns whether the rows were rewritten, after backing up the database as it was.
+fn migrate(connection: &mut Connection, path: &Path, backups: &Backups) -> Result<bool, StorageError> {
+    let database_error = |source| StorageError::Database {
+        path: path.to_path_buf(),
+        source,
+    };
+    let user_version = stored_version(connection).map_err(database_error)?;
+    // Databases from before versioning already stored version 1 items
+    let version = user_version.max(1);
+    if version >= SCHEMA_VERSION {
+        if user_version == 0 {
+            connection
+                .pragma_update(None, "user_version", SCHEMA_VERSION as i64)
+                .map_err(database_error)?;
+        }
+        return Ok(false);
+    }
+
+    let items = upgraded_items(connection, path, version)?;
+    backups.create(path)?;
+    write_items(connection, &items).map_err(database_error)?;
+    connection
+        .pragma_update(None, "user_version", SCHEMA_VERSION as i64)
+        .map_err(database_error)?;
+    Ok(true)
+}
+
+/// The schema version of the rows, `0` for a database from before versioning.
+fn stored_version(connection: &Connection) -> rusqlite::Result<u64> {
+    let user_version: i64 = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    Ok(user_version as u64)
+}
+
+/// The rows, stored at `version`, as items of the current schema.
+fn upgraded_items(connection: &Connection, path: &Path, version: u64) -> Result<Vec<TodoItem>, StorageError> {
+    let malformed = |source| StorageError::Malformed {
+        path: path.to_path_buf(),
+        source,
+    };
+    let rows = read_rows(connection)
+        .map_err(|source| StorageError::Database {
+            path: path.to_path_buf(),
+            source,
+        })?
+        .iter()
+        .map(|data| serde_json::from_str(data))
+        .collect::<Result<Vec<Value>, _>>()
+        .map_err(malformed)?;
+    let document = schema::upgrade(json!({ "schema_version": version, "items": rows }), path)?;
+    let document: TodoDocument = serde_json::from_value(document).map_err(malformed)?;
+    Ok(document.items)
+}
+
+fn insert_item(connection: &Connection, item: &TodoItem) -> rusqlite::Result<()> {
+    connection.execute(
+        "INSERT INTO todos (id, position, title, description, status, completed, data)
+         VALUES (?1, (SELECT COALESCE(MAX(position), 0) + 1 FROM todos), ?2, ?3, ?4, ?5, ?6)",
+        params![
+            item.id.to_string(),
+            item.title,
+            item.description,
+            item.status.to_str(),
+            item.is_completed(),
+            item_data(item)?
+        ],
+    )?;
+    Ok(())
+}
+
+fn update_item(connection: &Connection, item: &TodoItem) -> rusqlite::Result<()> {
+    connection.execute(
+        "UPDATE todos SET title = ?2, description = ?3, status = ?4, completed = ?5, data = ?6
+         WHERE id = ?1",
+        params![
+            item.id.to_string(),
+            item.title,
+            item.description,
+            item.status.to_str(),
+            item.is_completed(),
+            item_data(item)?
+        ],
+    )?;
+    Ok(())
+}
+
+fn delete_item(connection: &Connection, id: Uuid) -> rusqlite::Result<()> {
+    connection.execute("DELETE FROM todos WHERE id = ?1", params![id.to_string()])?;
+    Ok(())
+}
+
+fn apply_batch(connection: &mut Connection, batch: &StoreBatch) -> rusqlite::Result<()> {
+    let transaction = connection.transaction()?;
+    for &id in &batch.delete {
+        delete_item(&transaction, id)?;
+    }
+    for item in &batch.update {
+        update_item(&transaction, item)?;
+    }
+    for item in &batch.insert {
+        insert_item(&transaction, item)?;
+    }
+    transaction.commit()
+}
+
+fn item_data(item: &TodoItem) -> rusqlite::Result<String> {
+    serde_json::to_string(item).map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))
+}
+
+fn write_items(connection: &mut Connection, items: &[TodoItem]) -> rusqlite::Result<()> {
+    let transaction = connection.transaction()?;
+    transaction.execute("DELETE FROM todos", [])?;
+    for (position, item) in items.iter().enumerate() {
+        let data = item_data(item)?;
+        transaction.execute(
+            "INSERT INTO todos (id, position, title, description, status, completed, data)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                item.id.to_string(),
+                position as i64,
+                item.title,
+                item.description,
+                item.status.to_str(),
+                item.is_completed(),
+                data
+            ],
+        )?;
+    }
+    transaction.commit()
+}
+
+fn parse_item(data: &str, path: &Path) -> Result<TodoItem, StorageError> {
+    serde_json::from_str(data).map_err(|source| StorageError::Malformed {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::todo::TodoStatus;
+
+    /// A path for a database in a fresh temporary directory.
+    fn database(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("todo-sqlite-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("data.db")
+    }
+
+    fn item(title: &str) -> TodoItem {
+        TodoItem::new_todo_item(title.to_string(), String::new(), TodoStatus::default())
+    }
+
+    #[test]
+    fn a_failed_batch_changes_nothing() {
+        let path = database("batch");
+        let mut store = SqliteStore::new(path.clone(), Backups::new(&path, 0));
+        let kept = item("kept");
+        store.insert(&kept).unwrap();
+
+        let duplicate = item("duplicate");
+        let batch = StoreBatch {
+            insert: vec![duplicate.clone(), duplicate],
+            delete: vec![kept.id],
+            ..StoreBatch::default()
+        };
+        assert!(store.write_batch(&batch).is_err());
+
+        assert_eq!(store.load().unwrap(), vec![kept]);
+    }
+
+    /// Write a database at schema version 2 holding one completed item.
+    fn old_database(path: &Path) {
+        let connection = open(path).unwrap();
+        let data = json!({ "id": Uuid::new_v4(), "title": "old", "status": "Pending", "completed": true });
+        connection
+            .execute(
+                "INSERT INTO todos (id, position, title, description, status, completed, data)
+                 VALUES (?1, 0, 'old', '', 'Pending', 1, ?2)",
+                params![data["id"].as_str(), data.to_string()],
+            )
+            .unwrap();
+        connection.pragma_update(None, "user_version", 2).unwrap();
+    }
+
+    #[test]
+    fn old_databases_are_migrated_on_open() {
+        let path = database("migrate");
+        old_database(&path);
+
+        let mut store = SqliteStore::new(path.clone(), Backups::new(&path, 1));
+        let items = store.load().unwrap();
+
+        assert_eq!(items[0].status.name(), "Completed");
+        let version: i64 = open(&path)
+            .unwrap()
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, SCHEMA_VERSION as i64);
+        assert_eq!(store.list_backups().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn restoring_migrates_the_copy_but_not_the_backup() {
+        let path = database("restore");
+        old_database(&path);
+        let backup = Backups::new(&path, 1).create(&path).unwrap().unwrap();
+        let saved = fs::read(&backup).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // Room for the backup made when the restored copy is migrated
+        let mut store = SqliteStore::new(path.clone(), Backups::new(&path, 2));
+        store.restore_backup("latest").unwrap();
+
+        assert_eq!(store.load().unwrap()[0].status.name(), "Completed");
+        assert_eq!(fs::read(&backup).unwrap(), saved);
+    }
+
+    #[test]
+    fn a_backup_without_todos_is_not_restored() {
+        let path = database("foreign");
+        Connection::open(&path).unwrap().execute_batch("CREATE TABLE notes (text TEXT)").unwrap();
+        let backup = Backups::new(&path, 1).create(&path).unwrap().unwrap();
+        let saved = fs::read(&backup).unwrap();
+
+        let mut store = SqliteStore::new(path.clone(), Backups::new(&path, 1));
+        assert!(store.restore_backup("latest").is_err());
+        assert!(store.recover_from_backup().is_err());
+        assert_eq!(fs::read(&backup).unwrap(), saved);
+    }
+}
@@ -0,0 +1,60 @@
+use std::{fmt, io, path::PathBuf};
+
+#[derive(Debug)]
+pub enum StorageError {
+    /// The data file (or its directory) could not be read or written.
+    Io { path: PathBuf, source: io::Error },
+    /// The data file exists but does not contain valid todo JSON.
+    Malformed { path: PathBuf, source: serde_json::Error },
+    /// The data has a layout or version this build cannot read.
+    Schema { path: PathBuf, message: String },
+    /// The SQLite database could not be opened, read or written.
+    Database { path: PathBuf, source: rusqlite::Error },
+    /// The todos could not be turned into JSON.
+    Serialize(serde_json::Error),
+    /// No backup matches the requested name.
+    BackupNotFound(String),
+}
+
+impl StorageError {
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        StorageError::Io { path: path.into(), source }
+    }
+
+    pub fn is_malformed(&self) -> bool {
+        match self {
+            StorageError::Malformed { .. } | StorageError::Schema { .. } => true,
+            StorageError::Database { source, .. } => {
+                source.sqlite_error_code() == Some(rusqlite::ErrorCode::NotADatabase)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            StorageError::Malformed { path, source } => {
+                write!(f, "{} is not well-formatted: {}", path.display(), source)
+            }
+            StorageError::Schema { path, message } => write!(f, "{}: {}", path.display(), message),
+            StorageError::Database { path, source } => write!(f, "{}: {}", path.display(), source),
+            StorageError::Serialize(source) => write!(f, "Unable to serialize todos: {}", source),
+            StorageError::BackupNotFound(name) => write!(f, "No backup named {}", name),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StorageError::Io { source, .. } => Some(source),
+            StorageError::Malformed { source, .. } => Some(source),
+            StorageError::Database { source, .. } => Some(source),
+            StorageError::Serialize(source) => Some(source),
+            StorageError::Schema { .. } | StorageError::BackupNotFound(_) => None,
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::{fmt, path::PathBuf};
+
+use uuid::Uuid;
+
+use crate::{
+    storage::StorageError,
+    todo::{TodoItem, TodoStatus},
+};
+
+/// Where the todos are persisted.
+///
+/// `TodoList` keeps every item in memory for the TUI and writes each change
+/// through the store as it happens, so a store only has to persist the
+/// [`StoreBatch`] of items one action changed.
+pub trait TodoStore: fmt::Debug {
+    /// Read every item in display order, creating the backing file if needed.
+    fn load(&mut self) -> Result<Vec<TodoItem>, StorageError>;
+
+    /// Write all changes of the batch at once, either all of them are stored or none.
+    fn write_batch(&mut self, batch: &StoreBatch) -> Result<(), StorageError>;
+
+    /// Append a new item to the end of the list.
+    fn insert(&mut self, item: &TodoItem) -> Result<(), StorageError> {
+        self.write_batch(&StoreBatch {
+            insert: vec![item.clone()],
+            ..StoreBatch::default()
+        })
+    }
+
+    fn query(&mut self, query: &TodoQuery) -> Result<Vec<TodoItem>, StorageError>;
+
+    /// Replace everything in the store, used to resync after a failed write.
+    fn save_all(&mut self, items: &[TodoItem]) -> Result<(), StorageError>;
+
+    /// Backups of the store, newest first.
+    fn list_backups(&self) -> Result<Vec<PathBuf>, StorageError>;
+
+    /// Roll the store back to a backup, see [`Backups::find`](super::Backups::find) for the accepted names.
+    fn restore_backup(&mut self, name: &str) -> Result<PathBuf, StorageError>;
+
+    /// Replace unreadable data with the newest backup that can be read.
+    fn recover_from_backup(&mut self) -> Result<(), StorageError>;
+
+    /// Replace unreadable data with an empty store.
+    fn start_empty(&mut self) -> Result<(), StorageError>;
+}
+
+/// Items changed by one action, written with [`TodoStore::write_batch`].
+#[derive(Debug, Default)]
+pub struct StoreBatch {
+    /// New items, appended in this order
+    pub insert: Vec<TodoItem>,
+    /// Replace the stored items with the same ids
+    pub update: Vec<TodoItem>,
+    pub delete: Vec<Uuid>,
+}
+
+/// Filter for [`TodoStore::query`], every condition that is set has to match.
+#[derive(Debug, Default, Clone)]
+pub struct TodoQuery {
+    pub status: Option<TodoStatus>,
+    pub completed: Option<bool>,
+    /// Items need every one of these tags
+    pub tags: Vec<String>,
+    /// Case-insensitive substring of the title or the description
+    pub text: Option<String>,
+}
+
+impl TodoQuery {
+    pub fn matches(&self, item: &TodoItem) -> bool {
+        if self.status.as_ref().is_some_and(|status| *status != item.status) {
+            return false;
+        }
+        if self
+            .completed
+            .is_some_and(|completed| completed != item.is_completed())
+        {
+            return false;
+        }
+        if !self.tags.iter().all(|tag| item.tags.contains(tag)) {
+            return false;
+        }
+        if let Some(text) = &self.text {
+            let text = text.to_lowercase();
+            return item.title.to_lowercase().contains(&text)
+                || item.description.to_lowercase().contains(&text);
+        }
+        true
+    }
+}
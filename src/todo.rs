@@ -1,44 +1,302 @@
+use std::{
+    collections::BTreeSet,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
 
+use chrono::{DateTime, Days, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use ratatui::style::{Color, Modifier, Style};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use uuid::Uuid;
 
-use crate::form::form_inputs::enum_field::EnumDisplay;
+use crate::{dates::Due, form::form_inputs::enum_field::EnumDisplay, recurrence::Recurrence, workflow::workflow};
 
-#[derive(Debug, Deserialize, Serialize, Copy, Clone, PartialEq)]
-pub enum TodoStatus {
-    Completed,
-    InProgress,
-    Pending,
+/// The name of a status from the configured [`Workflow`](crate::workflow::Workflow).
+///
+/// Stored as a plain string, so todos keep their status even when it is no longer configured.
+/// Names are compared ignoring case, and a known status is read back in the spelling of the workflow.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(from = "String", into = "String")]
+pub struct TodoStatus(String);
+
+impl PartialEq for TodoStatus {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.eq_ignore_ascii_case(&other.0)
+    }
+}
+
+impl Eq for TodoStatus {}
+
+impl Hash for TodoStatus {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_ascii_lowercase().hash(state);
+    }
+}
+
+impl From<String> for TodoStatus {
+    fn from(name: String) -> Self {
+        workflow().find(&name).cloned().unwrap_or(TodoStatus(name))
+    }
+}
+
+impl From<TodoStatus> for String {
+    fn from(status: TodoStatus) -> Self {
+        status.0
+    }
+}
+
+impl TodoStatus {
+    pub fn new(name: impl Into<String>) -> TodoStatus {
+        TodoStatus(name.into())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    /// The next status of the workflow, the last one stays where it is.
+    pub fn next(&self) -> TodoStatus {
+        workflow().next(self)
+    }
+
+    /// The previous status of the workflow, the first one stays where it is.
+    pub fn previous(&self) -> TodoStatus {
+        workflow().previous(self)
+    }
+
+    pub fn is_completed(&self) -> bool {
+        workflow().is_done(self)
+    }
+}
+
+impl Default for TodoStatus {
+    fn default() -> Self {
+        workflow().initial().clone()
+    }
 }
 
 impl EnumDisplay for TodoStatus{
     fn to_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for TodoStatus {
+    type Err = String;
+
+    /// Look the name up in the workflow, ignoring case.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let workflow = workflow();
+        workflow.find(value).cloned().ok_or_else(|| {
+            let names: Vec<_> = workflow.statuses().iter().map(|status| status.name().to_string()).collect();
+            format!("unknown status {}, expected one of {}", value, names.join(", "))
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Priority {
+    #[default]
+    None,
+    Low,
+    Medium,
+    High,
+    Urgent,
+}
+
+impl Priority {
+    pub const ALL: [Priority; 5] = [Priority::None, Priority::Low, Priority::Medium, Priority::High, Priority::Urgent];
+
+    /// The next higher priority, Urgent wraps around to None.
+    pub fn next(&self) -> Priority {
+        let index = Priority::ALL.iter().position(|priority| priority == self).unwrap_or(0);
+        Priority::ALL[(index + 1) % Priority::ALL.len()]
+    }
+
+    pub fn is_none(&self) -> bool {
+        *self == Priority::None
+    }
+
+    pub fn style(&self) -> Style {
         match self {
-            TodoStatus::Completed => "Completed",
-            TodoStatus::InProgress => "InProgress",
-            TodoStatus::Pending => "Pending",
+            Priority::None => Style::new(),
+            Priority::Low => Style::new().fg(Color::Blue),
+            Priority::Medium => Style::new().fg(Color::Yellow),
+            Priority::High => Style::new().fg(Color::LightRed),
+            Priority::Urgent => Style::new().fg(Color::Red).add_modifier(Modifier::BOLD),
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+impl EnumDisplay for Priority {
+    fn to_str(&self) -> &str {
+        match self {
+            Priority::None => "None",
+            Priority::Low => "Low",
+            Priority::Medium => "Medium",
+            Priority::High => "High",
+            Priority::Urgent => "Urgent",
+        }
+    }
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Priority::ALL
+            .into_iter()
+            .find(|priority| priority.to_str().eq_ignore_ascii_case(value))
+            .ok_or_else(|| format!("unknown priority {}, expected none, low, medium, high or urgent", value))
+    }
+}
+
+/// The first characters of an id, enough to tell todos apart in practice.
+pub fn short_id(id: Uuid) -> String {
+    id.to_string()[..8].to_string()
+}
+
+/// Split comma or space separated tags. Tags are lowercase, a leading `#` is dropped.
+pub fn parse_tags(input: &str) -> BTreeSet<String> {
+    input
+        .split([',', ' '])
+        .map(|tag| tag.trim().trim_start_matches('#').to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct TodoItem {
+    #[serde(default = "Uuid::new_v4")]
     pub id: Uuid,
+    #[serde(default)]
     pub title: String,
+    #[serde(default)]
     pub description: String,
-    pub completed: bool,
+    /// The whole lifecycle of the item, it is completed exactly when this is a done status
+    #[serde(default)]
     pub status: TodoStatus,
+    #[serde(default, skip_serializing_if = "Priority::is_none")]
+    pub priority: Priority,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+    /// The item this is a subtask of
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<Uuid>,
+    /// Items that have to be completed before this one can be
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub depends_on: BTreeSet<Uuid>,
+    /// Repeat rule, see [`Recurrence`]. Kept as text so a rule this build does not
+    /// understand is not lost, it just does not repeat.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<NaiveDate>,
+    /// Only meaningful together with `due`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_time: Option<NaiveTime>,
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<DateTime<Utc>>,
+    /// Fields written by a newer build, kept so saving does not drop them
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 impl TodoItem {
     pub fn new_todo_item(title: String, description: String, status: TodoStatus) -> TodoItem {
+        let now = Utc::now();
         TodoItem {
             id: Uuid::new_v4(),
             title,
             description,
-            completed: false,
             status,
+            priority: Priority::None,
+            tags: BTreeSet::new(),
+            parent_id: None,
+            depends_on: BTreeSet::new(),
+            recurrence: None,
+            due: None,
+            due_time: None,
+            created_at: now,
+            updated_at: now,
+            completed_at: None,
+            extra: Map::new(),
         }
     }
+
+    /// Record that this item replaces `previous` at `now`, keeping the timestamps consistent.
+    pub fn touch(&mut self, previous: &TodoItem, now: DateTime<Utc>) {
+        self.created_at = previous.created_at;
+        self.updated_at = now;
+        self.completed_at = match (previous.is_completed(), self.is_completed()) {
+            (false, true) => Some(now),
+            (true, true) => previous.completed_at,
+            (_, false) => None,
+        };
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.status.is_completed()
+    }
+
+    /// Completing a completed item reopens it at the start of the workflow.
+    pub fn toggle_completed(&mut self) {
+        let workflow = workflow();
+        self.status = if self.is_completed() {
+            workflow.initial().clone()
+        } else {
+            workflow.done_status().clone()
+        };
+    }
+
+    pub fn recurrence(&self) -> Option<Recurrence> {
+        self.recurrence.as_deref()?.parse().ok()
+    }
+
+    /// The next occurrence of a repeating item that got completed `today`, as a new open item.
+    /// `None` when the item does not repeat or its next due date is out of range.
+    pub fn next_occurrence(&self, today: NaiveDate) -> Option<TodoItem> {
+        let rule = self.recurrence()?;
+        let anchored = rule.clone().anchored(self.due);
+        let due = anchored.next(self.due, today)?;
+        // Only spell out the rule again when it changed, so it reads as the user wrote it
+        let recurrence = match anchored == rule {
+            true => self.recurrence.clone(),
+            false => Some(anchored.to_string()),
+        };
+        let now = Utc::now();
+        Some(TodoItem {
+            id: Uuid::new_v4(),
+            status: TodoStatus::default(),
+            due: Some(due),
+            recurrence,
+            created_at: now,
+            updated_at: now,
+            completed_at: None,
+            ..self.clone()
+        })
+    }
+
+    pub fn set_due(&mut self, due: Option<Due>) {
+        self.due = due.map(|due| due.date);
+        self.due_time = due.and_then(|due| due.time);
+    }
+
+    /// An item without a time is due at the end of its day.
+    pub fn is_overdue(&self, now: NaiveDateTime) -> bool {
+        let Some(date) = self.due else {
+            return false;
+        };
+        let due_at = match self.due_time {
+            Some(time) => date.and_time(time),
+            None => date.and_time(NaiveTime::MIN) + Days::new(1),
+        };
+        !self.is_completed() && due_at <= now
+    }
 }
 
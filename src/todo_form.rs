@@ -3,7 +3,7 @@ use ratatui::{crossterm::event::{KeyCode, KeyEvent}, layout::{Constraint, Layout
 use serde::Serialize;
 use uuid::Uuid;
 
-use crate::{form::{form_inputs::{enum_field::EnumField, string_field::StringField, FormInputWidget}, form_status::FormStatus}, todo::{TodoItem, TodoStatus}};
+use crate::{form::{form_inputs::{date_field::DateField, enum_field::EnumField, string_field::StringField, tag_field::TagField, FormInputWidget}, form_status::FormStatus}, dates::Due, recurrence::Recurrence, todo::{self, Priority, TodoItem, TodoStatus}, todo_list::TodoList, workflow::workflow};
 
 
 #[derive(Serialize, Default, PartialEq, Eq, Debug)]
@@ -13,6 +13,11 @@ pub enum TodoFormState {
     Title,
     Description,
     Status,
+    Priority,
+    Tags,
+    DependsOn,
+    Due,
+    Repeat,
 }
 
 #[derive(Serialize, Debug)]
@@ -24,6 +29,21 @@ pub struct TodoForm {
     pub description: StringField,
     #[serde(skip)]
     pub status: EnumField<TodoStatus>,
+    #[serde(skip)]
+    pub priority: EnumField<Priority>,
+    pub tags: TagField,
+    /// Id prefixes of the items this one depends on
+    pub depends_on: StringField,
+    pub due: DateField,
+    /// Repeat rule, see [`Recurrence`]
+    pub repeat: StringField,
+    /// Why the last submit was rejected
+    pub error: Option<String>,
+    #[serde(skip)]
+    editing: Option<TodoItem>,
+    /// New items are added as subtasks of this one
+    #[serde(skip)]
+    parent: Option<Uuid>,
 }
 
 
@@ -35,7 +55,15 @@ impl Default for TodoForm {
             id: StringField::new("ID").set_default_value(Uuid::new_v4().to_string()),
             title: StringField::new("Title"),
             description: StringField::new("Description"),
-            status: EnumField::new("Status", &[TodoStatus::Pending, TodoStatus::InProgress, TodoStatus::Completed]),
+            status: status_field(None),
+            priority: EnumField::new("Priority", &Priority::ALL),
+            tags: TagField::new("Tags"),
+            depends_on: StringField::new("Depends on"),
+            due: DateField::new("Due"),
+            repeat: StringField::new("Repeat"),
+            error: None,
+            editing: None,
+            parent: None,
         }
     }
 }
@@ -47,6 +75,59 @@ impl TodoForm {
         self.form_status.reset();
         self.form_render_state = TodoFormState::default();
         self.id.set_value(Uuid::new_v4().to_string());
+        self.id.set_read_only(false);
+        self.status = status_field(None);
+        self.priority.set_value(Priority::None);
+        self.editing = None;
+        self.parent = None;
+    }
+
+    /// Fill the form with an existing item from `todos` so it can be edited in place.
+    /// The id is shown but cannot be changed. Dependencies on todos that are no
+    /// longer in the list are left out, they could not be saved again.
+    pub fn load_item(&mut self, item: &TodoItem, todos: &TodoList) {
+        self.reset();
+        self.id.set_value(item.id.to_string());
+        self.id.set_read_only(true);
+        self.title.set_value(item.title.clone());
+        self.description.set_value(item.description.clone());
+        self.status = status_field(Some(&item.status));
+        self.status.set_value(item.status.clone());
+        self.priority.set_value(item.priority);
+        self.tags.set_value(&item.tags);
+        let depends_on: Vec<_> = item
+            .depends_on
+            .iter()
+            .filter(|id| todos.items.iter().any(|other| other.id == **id))
+            .map(|id| todo::short_id(*id))
+            .collect();
+        self.depends_on.set_value(depends_on.join(", "));
+        self.due.set_value(item.due.map(|date| Due::new(date, item.due_time)));
+        self.repeat.set_value(item.recurrence.clone().unwrap_or_default());
+        self.editing = Some(item.clone());
+    }
+
+    /// Add the next item as a subtask of `parent`.
+    pub fn set_parent(&mut self, parent: Option<Uuid>) {
+        self.parent = parent;
+    }
+
+    pub fn parent(&self) -> Option<Uuid> {
+        self.parent
+    }
+
+    /// The typed id prefixes, resolving them needs the whole list.
+    pub fn dependency_prefixes(&self) -> Vec<String> {
+        self.depends_on
+            .get_value()
+            .split([',', ' '])
+            .filter(|prefix| !prefix.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    pub fn is_editing_item(&self) -> bool {
+        self.editing.is_some()
     }
 
     pub fn on_key_press(&mut self, key: KeyCode, key_event: KeyEvent) {
@@ -58,11 +139,9 @@ impl TodoForm {
         match key {
             KeyCode::Tab => {
                 self.next_field();
-                return;
             }
             KeyCode::BackTab => {
                 self.previous_field();
-                return;
             }
             _ => {
                 self.current_field_mut().on_key_press(key);
@@ -71,7 +150,12 @@ impl TodoForm {
     }
 
     pub fn render(&self, area: Rect, frame: &mut Frame) {
-        let [id_area, title_area, des_area, status_area] = Layout::vertical([
+        let [id_area, title_area, des_area, status_area, priority_area, tags_area, depends_on_area, due_area, repeat_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
             Constraint::Length(3),
             Constraint::Length(3),
             Constraint::Length(3),
@@ -84,12 +168,22 @@ impl TodoForm {
         frame.render_widget(&self.title, title_area);
         frame.render_widget(&self.description, des_area);
         frame.render_widget(&self.status, status_area);
+        frame.render_widget(&self.priority, priority_area);
+        frame.render_widget(&self.tags, tags_area);
+        frame.render_widget(&self.depends_on, depends_on_area);
+        frame.render_widget(&self.due, due_area);
+        frame.render_widget(&self.repeat, repeat_area);
 
         let cursor_position = match self.form_render_state {
             TodoFormState::Id => id_area.offset(self.id.cursor_offset()),
             TodoFormState::Title => title_area.offset(self.title.cursor_offset()),
             TodoFormState::Description => des_area.offset(self.description.cursor_offset()),
             TodoFormState::Status => status_area.offset(Offset { x: 0, y: 0 }),
+            TodoFormState::Priority => priority_area.offset(Offset { x: 0, y: 0 }),
+            TodoFormState::Tags => tags_area.offset(self.tags.cursor_offset()),
+            TodoFormState::DependsOn => depends_on_area.offset(self.depends_on.cursor_offset()),
+            TodoFormState::Due => due_area.offset(self.due.cursor_offset()),
+            TodoFormState::Repeat => repeat_area.offset(self.repeat.cursor_offset()),
         };
         frame.set_cursor_position(cursor_position);
     }
@@ -99,15 +193,53 @@ impl TodoForm {
             return None;
         }
 
-        if self.title.is_empty() || self.description.is_empty() {
+        if self.validate().is_err() {
             return None;
         }
+        let due = self.due.get_value().ok()?;
+        let recurrence = self.recurrence().ok()?.map(|recurrence| recurrence.to_string());
 
-        Some(TodoItem::new_todo_item(
+        if let Some(original) = &self.editing {
+            let mut item = TodoItem {
+                title: self.title.get_value().to_string(),
+                description: self.description.get_value().to_string(),
+                status: self.status.get_value(),
+                priority: self.priority.get_value(),
+                tags: self.tags.get_value(),
+                recurrence,
+                ..original.clone()
+            };
+            item.set_due(due);
+            return Some(item);
+        }
+
+        let mut item = TodoItem::new_todo_item(
             self.title.get_value().to_string(), 
             self.description.get_value().to_string(), 
-            self.status.get_value())
-        )
+            self.status.get_value()
+        );
+        item.priority = self.priority.get_value();
+        item.tags = self.tags.get_value();
+        item.parent_id = self.parent;
+        item.recurrence = recurrence;
+        item.set_due(due);
+        Some(item)
+    }
+
+    /// Check the fields before submitting, with a message to show the user.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.title.is_empty() {
+            return Err("Title is required".to_string());
+        }
+        self.due.get_value().map_err(|err| format!("Due: {}", err))?;
+        self.recurrence().map(|_| ()).map_err(|err| format!("Repeat: {}", err))
+    }
+
+    fn recurrence(&self) -> Result<Option<Recurrence>, String> {
+        match self.repeat.get_value().trim() {
+            "" => Ok(None),
+            rule => rule.parse().map(Some),
+        }
     }
 
     fn current_field_mut(&mut self) -> &mut dyn FormInputWidget {
@@ -116,6 +248,11 @@ impl TodoForm {
             TodoFormState::Title => &mut self.title,
             TodoFormState::Description => &mut self.description,
             TodoFormState::Status => &mut self.status,
+            TodoFormState::Priority => &mut self.priority,
+            TodoFormState::Tags => &mut self.tags,
+            TodoFormState::DependsOn => &mut self.depends_on,
+            TodoFormState::Due => &mut self.due,
+            TodoFormState::Repeat => &mut self.repeat,
         }
     }
 
@@ -125,6 +262,11 @@ impl TodoForm {
             TodoFormState::Title => &self.title,
             TodoFormState::Description => &self.description,
             TodoFormState::Status => &self.status,
+            TodoFormState::Priority => &self.priority,
+            TodoFormState::Tags => &self.tags,
+            TodoFormState::DependsOn => &self.depends_on,
+            TodoFormState::Due => &self.due,
+            TodoFormState::Repeat => &self.repeat,
         }
     }
 
@@ -133,16 +275,26 @@ impl TodoForm {
             TodoFormState::Id => TodoFormState::Title,
             TodoFormState::Title => TodoFormState::Description,
             TodoFormState::Description => TodoFormState::Status,
-            TodoFormState::Status => TodoFormState::Id,
+            TodoFormState::Status => TodoFormState::Priority,
+            TodoFormState::Priority => TodoFormState::Tags,
+            TodoFormState::Tags => TodoFormState::DependsOn,
+            TodoFormState::DependsOn => TodoFormState::Due,
+            TodoFormState::Due => TodoFormState::Repeat,
+            TodoFormState::Repeat => TodoFormState::Id,
         }
     }
 
     pub fn previous_field(&mut self) {
         self.form_render_state = match self.form_render_state {
-            TodoFormState::Id => TodoFormState::Status,
+            TodoFormState::Id => TodoFormState::Repeat,
             TodoFormState::Title => TodoFormState::Id,
             TodoFormState::Description => TodoFormState::Title,
             TodoFormState::Status => TodoFormState::Description,
+            TodoFormState::Priority => TodoFormState::Status,
+            TodoFormState::Tags => TodoFormState::Priority,
+            TodoFormState::DependsOn => TodoFormState::Tags,
+            TodoFormState::Due => TodoFormState::DependsOn,
+            TodoFormState::Repeat => TodoFormState::Due,
         }
     }
 
@@ -150,6 +302,11 @@ impl TodoForm {
         self.id.clear();
         self.title.clear();
         self.description.clear();
+        self.due.clear();
+        self.tags.clear();
+        self.depends_on.clear();
+        self.repeat.clear();
+        self.error = None;
         // self.status.clear();
     }
 
@@ -157,3 +314,14 @@ impl TodoForm {
         !self.title.is_empty() && !self.description.is_empty()
     }
 }
+
+/// The workflow statuses, plus `current` when an edited item has a status that is no longer configured.
+fn status_field(current: Option<&TodoStatus>) -> EnumField<TodoStatus> {
+    let mut options = workflow().statuses();
+    if let Some(current) = current
+        && !options.contains(current)
+    {
+        options.push(current.clone());
+    }
+    EnumField::with_options("Status", options)
+}
@@ -1,79 +1,1521 @@
-use std::fs;
-use ratatui::widgets::ListState;
+use std::{
+    cmp::Ordering,
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt,
+    ops::Range,
+    path::PathBuf,
+};
 
-use crate::{form::form_inputs::enum_field::EnumDisplay, todo::TodoItem};
+use chrono::Utc;
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span, Text},
+    widgets::ListState,
+};
+use uuid::Uuid;
 
-#[derive(Debug, Default)]
+use crate::{
+    dates,
+    form::form_inputs::enum_field::EnumDisplay,
+    history::ItemChange,
+    query::Query,
+    storage::{StorageError, StoreBatch, TodoQuery, TodoStore},
+    todo::{TodoItem, TodoStatus},
+    workflow::workflow,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    Created,
+    Updated,
+    Completed,
+    Due,
+    Title,
+    Priority,
+}
+
+impl SortKey {
+    /// Ascending order, items without the value (no due date, not completed) go last.
+    /// Priorities are the exception, the most urgent comes first.
+    pub fn compare(&self, a: &TodoItem, b: &TodoItem) -> Ordering {
+        fn some_first<T: Ord>(a: Option<T>, b: Option<T>) -> Ordering {
+            match (a, b) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (a, b) => b.is_some().cmp(&a.is_some()),
+            }
+        }
+        match self {
+            SortKey::Created => a.created_at.cmp(&b.created_at),
+            SortKey::Updated => a.updated_at.cmp(&b.updated_at),
+            SortKey::Completed => some_first(a.completed_at, b.completed_at),
+            SortKey::Due => some_first(a.due, b.due).then_with(|| some_first(a.due_time, b.due_time)),
+            SortKey::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+            SortKey::Priority => b.priority.cmp(&a.priority),
+        }
+    }
+}
+
+/// Order of the List panel: most urgent first, then by due date.
+pub fn display_order(a: &TodoItem, b: &TodoItem) -> Ordering {
+    SortKey::Priority
+        .compare(a, b)
+        .then_with(|| SortKey::Due.compare(a, b))
+}
+
+#[derive(Debug)]
 pub struct TodoList {
+    /// Name of the list, see [`ListLocator`](crate::storage::ListLocator)
+    name: String,
     pub items: Vec<TodoItem>,
+    /// Selection in the List panel, an index into `view`
     pub state: ListState,
+    /// The visible rows of the tree, in display order
+    view: Vec<ViewRow>,
+    /// Completed and total subtasks below every item that has any, updated along with `view`
+    progress: HashMap<Uuid, (usize, usize)>,
+    /// Items whose subtasks are hidden
+    collapsed: HashSet<Uuid>,
+    /// Only items with this tag are shown
+    tag_filter: Option<String>,
+    /// Only items matching this are shown
+    filter: Option<Query>,
+    /// Only items whose title or description contains this are shown, with the items above them
+    search: String,
+    /// Items marked for a bulk action
+    marked: HashSet<Uuid>,
+    /// Where the range being marked started
+    range_start: Option<Uuid>,
+    store: Box<dyn TodoStore>,
+    /// Set when a write to the store failed, the next write resyncs everything
+    dirty: bool,
+    /// Set once the items were read from the store
+    loaded: bool,
+    /// What changed since the last [`TodoList::take_changes`], for the undo history
+    changes: Vec<ItemChange>,
 }
 
 impl TodoList {
-    pub fn read_todos(&mut self) {
-        let path = ".\\data\\data.json";
-        let data = fs::read_to_string(path).expect("Unable to read file");
-        if data.is_empty() {
-            self.items = Vec::new();
-            self.state = ListState::default();
-            return;
+    pub fn new(name: impl Into<String>, store: Box<dyn TodoStore>) -> Self {
+        Self {
+            name: name.into(),
+            items: Vec::new(),
+            state: ListState::default(),
+            view: Vec::new(),
+            progress: HashMap::new(),
+            collapsed: HashSet::new(),
+            tag_filter: None,
+            filter: None,
+            search: String::new(),
+            marked: HashSet::new(),
+            range_start: None,
+            store,
+            dirty: false,
+            loaded: false,
+            changes: Vec::new(),
         }
-        let todos: Vec<TodoItem> = serde_json::from_str(&data).expect("JSON was not well-formatted");
-        
-        self.items = todos;
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+
+    pub fn read_todos(&mut self) -> Result<(), StorageError> {
+        self.items = self.store.load()?;
         self.state = ListState::default();
+        self.marked.clear();
+        self.changes.clear();
+        self.refresh_view(None);
+        self.dirty = false;
+        self.loaded = true;
+        Ok(())
     }
 
-    pub fn save_todos(&self) {
-        let path = ".\\data\\data.json";
-        let data = serde_json::to_string(&self.items).expect("Unable to serialize data");
-        fs::write(path, data).expect("Unable to write file");
+    /// Recover from malformed data by loading the newest backup that can be read.
+    /// The malformed file is kept next to the original with an extra `.corrupt` extension.
+    pub fn read_backup(&mut self) -> Result<(), StorageError> {
+        self.store.recover_from_backup()?;
+        self.read_todos()
     }
 
-    pub fn remove_selected(&mut self) {
-        if let Some(selected) = self.state.selected() {
-            self.items.remove(selected);
-            let new_len = self.items.len();
-            if new_len == 0 {
-                self.state.select(None);
-            } else if selected >= new_len {
-                self.state.select(Some(new_len - 1));
-            } else {
-                self.state.select(Some(selected));
+    /// Recover from malformed data by starting with no todos.
+    /// The malformed file is kept next to the original with an extra `.corrupt` extension.
+    pub fn start_empty(&mut self) -> Result<(), StorageError> {
+        self.store.start_empty()?;
+        self.read_todos()
+    }
+
+    /// Roll the data back to one of its backups, see [`Backups::find`](crate::storage::Backups::find)
+    /// for the accepted names. The current data is backed up first so the restore can itself be undone.
+    pub fn restore_backup(&mut self, name: &str) -> Result<PathBuf, StorageError> {
+        let backup = self.store.restore_backup(name)?;
+        self.read_todos()?;
+        Ok(backup)
+    }
+
+    pub fn list_backups(&self) -> Result<Vec<PathBuf>, StorageError> {
+        self.store.list_backups()
+    }
+
+    pub fn query(&mut self, query: &TodoQuery) -> Result<Vec<TodoItem>, StorageError> {
+        self.store.query(query)
+    }
+
+    /// Changes are written through as they happen, this only has work to do
+    /// when an earlier write failed.
+    pub fn save_todos(&mut self) -> Result<(), StorageError> {
+        if !self.dirty {
+            return Ok(());
+        }
+        self.persist(|_| Ok(()))
+    }
+
+    /// Write a single change to the store. After a failed write the in-memory
+    /// items are the source of truth, so everything is rewritten instead.
+    fn persist(
+        &mut self,
+        write: impl FnOnce(&mut dyn TodoStore) -> Result<(), StorageError>,
+    ) -> Result<(), StorageError> {
+        let result = if self.dirty {
+            self.store.save_all(&self.items)
+        } else {
+            write(self.store.as_mut())
+        };
+        self.dirty = result.is_err();
+        result
+    }
+
+    /// Add a new item. A completed item is refused while one of its dependencies is open.
+    pub fn add_item(&mut self, item: TodoItem) -> Result<(), ChangeError> {
+        if item.is_completed() {
+            let blockers: Vec<String> = self
+                .open_dependencies(&item)
+                .map(|dependency| dependency.title.clone())
+                .collect();
+            if !blockers.is_empty() {
+                return Err(ChangeError::Blocked(item.title, blockers));
+            }
+        }
+        let selected = self.selected_id();
+        let item = self.push_new(item);
+        self.refresh_view(selected);
+        Ok(self.persist(|store| store.insert(&item))?)
+    }
+
+    /// Add a new item to the list and journal it, the caller writes it to the store.
+    fn push_new(&mut self, mut item: TodoItem) -> TodoItem {
+        let now = Utc::now();
+        item.created_at = now;
+        item.updated_at = now;
+        item.completed_at = item.is_completed().then_some(now);
+        self.items.push(item.clone());
+        self.changes.push(ItemChange {
+            before: None,
+            after: Some(item.clone()),
+        });
+        item
+    }
+
+    /// Move the item and its subtasks to `target`, keeping their ids and timestamps.
+    /// The item becomes a top level todo there. Dependencies between the moved todos
+    /// and the ones that stay behind are dropped both ways, the todos that stay lose
+    /// theirs through [`TodoList::remove_item`]. Returns how many todos were moved.
+    ///
+    /// The todos are written to `target` before they are removed here, so a failed
+    /// write leaves a copy behind rather than losing them.
+    pub fn move_item(&mut self, id: Uuid, target: &mut TodoList) -> Result<usize, StorageError> {
+        self.move_items(&[id], target)
+    }
+
+    /// Move several items with their subtasks, writing each list once, see [`TodoList::move_item`].
+    fn move_items(&mut self, ids: &[Uuid], target: &mut TodoList) -> Result<usize, StorageError> {
+        let moved = self.with_descendants(ids);
+        let items: Vec<TodoItem> = self
+            .items
+            .iter()
+            .filter(|item| moved.contains(&item.id))
+            .cloned()
+            .map(|mut item| {
+                if item.parent_id.is_some_and(|parent| !moved.contains(&parent)) {
+                    item.parent_id = None;
+                }
+                item.depends_on.retain(|dependency| moved.contains(dependency));
+                item
+            })
+            .collect();
+        let count = items.len();
+        if count == 0 {
+            return Ok(0);
+        }
+
+        let selected = target.selected_id();
+        target.items.extend(items.iter().cloned());
+        target.refresh_view(selected);
+        target.changes.extend(items.iter().map(|item| ItemChange {
+            before: None,
+            after: Some(item.clone()),
+        }));
+        let batch = StoreBatch {
+            insert: items,
+            ..StoreBatch::default()
+        };
+        target.persist(|store| store.write_batch(&batch))?;
+        self.remove_items(&moved)?;
+        Ok(count)
+    }
+
+    pub fn remove_selected(&mut self) -> Result<(), StorageError> {
+        match self.get_selected() {
+            Some(item) => self.remove_item(item.id),
+            None => Ok(()),
+        }
+    }
+
+    /// Remove the item together with all of its subtasks. The remaining items no
+    /// longer depend on them, a dependency on a removed todo could never be completed.
+    pub fn remove_item(&mut self, id: Uuid) -> Result<(), StorageError> {
+        self.remove_items(&[id]).map(|_| ())
+    }
+
+    /// Remove several items with their subtasks in a single write, returns how many were removed.
+    fn remove_items(&mut self, ids: &[Uuid]) -> Result<usize, StorageError> {
+        let removed = self.with_descendants(ids);
+        if removed.is_empty() {
+            return Ok(0);
+        }
+        let selected = self.selected_id();
+        for item in self.items.iter().filter(|item| removed.contains(&item.id)) {
+            self.changes.push(ItemChange {
+                before: Some(item.clone()),
+                after: None,
+            });
+        }
+        self.items.retain(|item| !removed.contains(&item.id));
+        let now = Utc::now();
+        let mut updated = Vec::new();
+        for item in self.items.iter_mut() {
+            if !item.depends_on.iter().any(|dependency| removed.contains(dependency)) {
+                continue;
             }
+            let before = item.clone();
+            item.depends_on.retain(|dependency| !removed.contains(dependency));
+            item.updated_at = now;
+            self.changes.push(ItemChange {
+                before: Some(before),
+                after: Some(item.clone()),
+            });
+            updated.push(item.clone());
         }
+        self.refresh_view(selected);
+        let count = removed.len();
+        let batch = StoreBatch {
+            update: updated,
+            delete: removed,
+            ..StoreBatch::default()
+        };
+        self.persist(|store| store.write_batch(&batch))?;
+        Ok(count)
     }
 
-    pub fn mark_completed(&mut self) {
-        if let Some(selected) = self.state.selected() {
-            if let Some(item) = self.items.get_mut(selected) {
-                item.completed = !item.completed;
+    /// The changes made since the last call, one per item. An item that was
+    /// changed several times shows up once, and one that was added and removed again not at all.
+    pub fn take_changes(&mut self) -> Vec<ItemChange> {
+        let mut changes: Vec<ItemChange> = Vec::new();
+        for change in self.changes.drain(..) {
+            match changes.iter_mut().find(|merged| merged.id() == change.id()) {
+                Some(merged) => merged.after = change.after,
+                None => changes.push(change),
             }
         }
+        changes.retain(|change| change.before != change.after);
+        changes
     }
 
-    pub fn get_list_to_display(&self) -> Vec<String> {
-        self.items.iter().map(|item| {
-            let status = if item.completed { "[✓]" } else { "[✗]" };
-            format!("{} {} - {}", status, item.title, item.status.to_str())
-        }).collect()
+    /// Whether every item is still as the changes found it, so that [`TodoList::apply`]
+    /// does not overwrite anything done since.
+    pub fn is_unchanged_since(&self, changes: &[ItemChange]) -> bool {
+        changes.iter().all(|change| {
+            let current = self.items.iter().find(|item| item.id == change.id());
+            current == change.before.as_ref()
+        })
+    }
+
+    /// Put every item in the state after the changes, exactly as it was then.
+    /// This is how changes are undone and redone, so it is not itself recorded.
+    pub fn apply(&mut self, changes: &[ItemChange]) -> Result<(), StorageError> {
+        let selected = self.selected_id();
+        let position = |items: &[TodoItem], id| items.iter().position(|item| item.id == id);
+        for change in changes {
+            match (&change.after, position(&self.items, change.id())) {
+                (Some(item), Some(index)) => self.items[index] = item.clone(),
+                (Some(item), None) => self.items.push(item.clone()),
+                (None, Some(index)) => {
+                    self.items.remove(index);
+                }
+                (None, None) => {}
+            }
+        }
+        self.refresh_view(selected);
+        let mut batch = StoreBatch::default();
+        for change in changes {
+            match (&change.before, &change.after) {
+                (Some(_), Some(item)) => batch.update.push(item.clone()),
+                (None, Some(item)) => batch.insert.push(item.clone()),
+                (Some(_), None) => batch.delete.push(change.id()),
+                (None, None) => {}
+            }
+        }
+        self.persist(|store| store.write_batch(&batch))
+    }
+
+    /// Rebuild the display order after the items changed. The selection follows the
+    /// `selected` item, or stays at the same row when that item is gone.
+    ///
+    /// Subtasks are shown below their parent. When the parent is filtered out
+    /// the subtask is shown at the top level instead. While searching, the items
+    /// above a match are kept to show where it is, and nothing is collapsed.
+    fn refresh_view(&mut self, selected: Option<Uuid>) {
+        let mut context = HashSet::new();
+        for item in self.items.iter().filter(|item| self.is_match(item)) {
+            context.insert(item.id);
+            context.extend(self.ancestors(item.id));
+        }
+        let visible: Vec<usize> = (0..self.items.len())
+            .filter(|&index| {
+                let item = &self.items[index];
+                self.passes_filters(item) && (self.search.is_empty() || context.contains(&item.id))
+            })
+            .collect();
+        let visible_ids: HashSet<Uuid> = visible.iter().map(|&index| self.items[index].id).collect();
+        let mut children: HashMap<Option<Uuid>, Vec<usize>> = HashMap::new();
+        for &index in &visible {
+            let parent = self.items[index].parent_id.filter(|parent| visible_ids.contains(parent));
+            children.entry(parent).or_default().push(index);
+        }
+        for siblings in children.values_mut() {
+            siblings.sort_by(|&a, &b| display_order(&self.items[a], &self.items[b]));
+        }
+
+        // Start from the top level items; anything left over is part of a parent cycle
+        let roots = children.get(&None).cloned().unwrap_or_default();
+        let mut view = Vec::new();
+        let mut seen = HashSet::new();
+        for start in roots.into_iter().chain(visible) {
+            let mut stack = vec![(ViewRow { index: start, depth: 0 }, false)];
+            while let Some((row, hidden)) = stack.pop() {
+                if !seen.insert(row.index) {
+                    continue;
+                }
+                if !hidden {
+                    view.push(row);
+                }
+                let id = self.items[row.index].id;
+                let hidden = hidden || self.is_collapsed(id);
+                for &child in children.get(&Some(id)).into_iter().flatten().rev() {
+                    stack.push((ViewRow { index: child, depth: row.depth + 1 }, hidden));
+                }
+            }
+        }
+        self.view = view;
+        self.progress = self.count_progress();
+
+        let position = selected.and_then(|id| self.view.iter().position(|row| self.items[row.index].id == id));
+        let len = self.view.len();
+        match (position, self.state.selected()) {
+            (Some(position), _) => self.state.select(Some(position)),
+            (None, Some(_)) if len == 0 => self.state.select(None),
+            (None, Some(selected)) if selected >= len => self.state.select(Some(len - 1)),
+            _ => {}
+        }
+    }
+
+    /// Find an item by its id or any unique prefix of it, as typed on the command line.
+    pub fn find_by_id_prefix(&self, prefix: &str) -> Result<&TodoItem, IdLookupError> {
+        let prefix = prefix.to_lowercase();
+        let mut matches = self
+            .items
+            .iter()
+            .filter(|item| item.id.to_string().starts_with(&prefix));
+        match (matches.next(), matches.count()) {
+            (Some(item), 0) => Ok(item),
+            (Some(_), others) => Err(IdLookupError::Ambiguous(prefix, others + 1)),
+            (None, _) => Err(IdLookupError::NotFound(prefix)),
+        }
     }
 
-    pub fn get_selected_item_display(&self) -> String {
-        let selected = match self.state.selected() {
-            Some(idx) => idx,
-            None => return "No item selected".to_string(),
+    /// Complete the selected item, or reopen it if it already is.
+    pub fn mark_completed(&mut self) -> Result<(), ChangeError> {
+        let Some(mut item) = self.get_selected().cloned() else {
+            return Ok(());
         };
-        if let Some(item) = self.items.get(selected) {
-            format!("title: {}, id: {} \n description: \n {} \n completed: \n {} \n status: \n {} ", 
-                item.title.clone(),
-                item.id.to_string(),
-                item.description.clone(),
-                item.completed.to_string(),
-                item.status.to_str().to_string()
-            )
+        item.toggle_completed();
+        self.update_item(item)
+    }
+
+    pub fn marked_count(&self) -> usize {
+        self.items.iter().filter(|item| self.marked.contains(&item.id)).count()
+    }
+
+    pub fn is_marking_range(&self) -> bool {
+        self.range_start.is_some()
+    }
+
+    pub fn toggle_mark_selected(&mut self) {
+        if let Some(id) = self.selected_id()
+            && !self.marked.remove(&id)
+        {
+            self.marked.insert(id);
+        }
+    }
+
+    /// The first call remembers the selected row, the second marks every row
+    /// from there to the selected one.
+    pub fn mark_range(&mut self) {
+        // The selection can point past the rows, e.g. after a search that matches nothing
+        let Some(end) = self.state.selected().filter(|_| !self.view.is_empty()) else {
+            return;
+        };
+        let end = end.min(self.view.len() - 1);
+        let Some(start) = self.range_start.take() else {
+            self.range_start = self.selected_id();
+            return;
+        };
+        let start = self
+            .view
+            .iter()
+            .position(|row| self.items[row.index].id == start)
+            .unwrap_or(end);
+        for row in &self.view[start.min(end)..=start.max(end)] {
+            self.marked.insert(self.items[row.index].id);
+        }
+    }
+
+    /// Mark every shown item, or clear the marks when they all are marked already.
+    pub fn mark_all(&mut self) {
+        let shown: HashSet<Uuid> = self.view.iter().map(|row| self.items[row.index].id).collect();
+        if shown.is_subset(&self.marked) {
+            self.marked.retain(|id| !shown.contains(id));
         } else {
-            "No item selected".to_string()
+            self.marked.extend(shown);
+        }
+    }
+
+    /// Clear the marks, returns whether there were any.
+    pub fn clear_marks(&mut self) -> bool {
+        self.range_start = None;
+        let had_marks = !self.marked.is_empty();
+        self.marked.clear();
+        had_marks
+    }
+
+    /// What a bulk action works on: the marked items in list order, or the selected one.
+    pub fn targets(&self) -> Vec<Uuid> {
+        if self.marked.is_empty() {
+            return self.selected_id().into_iter().collect();
+        }
+        self.items
+            .iter()
+            .map(|item| item.id)
+            .filter(|id| self.marked.contains(id))
+            .collect()
+    }
+
+    /// Apply `change` to each of the items, then clear the marks and write every change
+    /// at once. Items that could not be changed, e.g. because they are blocked, are skipped.
+    pub fn update_many(
+        &mut self,
+        ids: &[Uuid],
+        change: impl Fn(&mut TodoItem),
+    ) -> Result<BulkSummary, StorageError> {
+        let selected = self.selected_id();
+        let mut summary = BulkSummary::default();
+        let mut batch = StoreBatch::default();
+        for id in ids {
+            let Some(mut item) = self.items.iter().find(|item| item.id == *id).cloned() else {
+                continue;
+            };
+            let before = item.clone();
+            change(&mut item);
+            if item == before {
+                continue;
+            }
+            match self.stage_update(item, &mut batch) {
+                Ok(()) => summary.changed += 1,
+                Err(err) => summary.skipped.push(err.to_string()),
+            }
+        }
+        self.clear_marks();
+        if !batch.update.is_empty() {
+            self.refresh_view(selected);
+            self.persist(|store| store.write_batch(&batch))?;
+        }
+        Ok(summary)
+    }
+
+    pub fn all_completed(&self, ids: &[Uuid]) -> bool {
+        ids.iter()
+            .filter_map(|id| self.items.iter().find(|item| item.id == *id))
+            .all(|item| item.is_completed())
+    }
+
+    /// Complete the open items, or reopen the completed ones.
+    pub fn complete_many(&mut self, ids: &[Uuid], reopen: bool) -> Result<BulkSummary, StorageError> {
+        self.update_many(ids, |item| {
+            if item.is_completed() == reopen {
+                item.toggle_completed();
+            }
+        })
+    }
+
+    /// Remove the items with their subtasks. Counts every removed item, subtasks included.
+    pub fn remove_many(&mut self, ids: &[Uuid]) -> Result<BulkSummary, StorageError> {
+        let summary = BulkSummary {
+            changed: self.remove_items(ids)?,
+            ..BulkSummary::default()
+        };
+        self.clear_marks();
+        Ok(summary)
+    }
+
+    /// Move the items with their subtasks to `target`, see [`TodoList::move_item`].
+    pub fn move_many(&mut self, ids: &[Uuid], target: &mut TodoList) -> Result<BulkSummary, StorageError> {
+        let summary = BulkSummary {
+            changed: self.move_items(ids, target)?,
+            ..BulkSummary::default()
+        };
+        self.clear_marks();
+        Ok(summary)
+    }
+
+    /// Move the selected item one step forward in its lifecycle.
+    pub fn advance_selected(&mut self) -> Result<(), ChangeError> {
+        self.set_selected_status(|status| status.next())
+    }
+
+    /// Move the selected item one step back in its lifecycle.
+    pub fn regress_selected(&mut self) -> Result<(), ChangeError> {
+        self.set_selected_status(|status| status.previous())
+    }
+
+    pub fn set_selected_status(&mut self, change: impl FnOnce(&TodoStatus) -> TodoStatus) -> Result<(), ChangeError> {
+        let Some(mut item) = self.get_selected().cloned() else {
+            return Ok(());
+        };
+        let status = change(&item.status);
+        if status == item.status {
+            return Ok(());
+        }
+        item.status = status;
+        self.update_item(item)
+    }
+
+    /// Raise the priority of the selected item, from Urgent back to None.
+    pub fn cycle_selected_priority(&mut self) -> Result<(), ChangeError> {
+        let Some(mut item) = self.get_selected().cloned() else {
+            return Ok(());
+        };
+        item.priority = item.priority.next();
+        self.update_item(item)
+    }
+
+    /// Every tag in use, sorted.
+    pub fn tags(&self) -> Vec<String> {
+        let tags: BTreeSet<&String> = self.items.iter().flat_map(|item| &item.tags).collect();
+        tags.into_iter().cloned().collect()
+    }
+
+    pub fn tag_filter(&self) -> Option<&str> {
+        self.tag_filter.as_deref()
+    }
+
+    /// Filter by the next tag in use, after the last one the filter is cleared.
+    pub fn cycle_tag_filter(&mut self) {
+        let tags = self.tags();
+        let next = match &self.tag_filter {
+            Some(current) => tags.iter().position(|tag| tag == current).and_then(|index| tags.get(index + 1)),
+            None => tags.first(),
+        };
+        self.tag_filter = next.cloned();
+        self.refresh_view(self.selected_id());
+    }
+
+    /// The tag filter and the filter query, the search is not included.
+    fn passes_filters(&self, item: &TodoItem) -> bool {
+        self.tag_filter.as_ref().is_none_or(|tag| item.tags.contains(tag))
+            && self.filter.as_ref().is_none_or(|filter| filter.matches(item, self))
+    }
+
+    /// Every item that passes the filters and the search, without the tree around it.
+    pub fn filtered_items(&self) -> impl Iterator<Item = &TodoItem> {
+        self.items
+            .iter()
+            .filter(|item| self.passes_filters(item) && (self.search.is_empty() || self.is_match(item)))
+    }
+
+    pub fn filter(&self) -> Option<&Query> {
+        self.filter.as_ref()
+    }
+
+    pub fn set_filter(&mut self, filter: Option<Query>) {
+        self.filter = filter;
+        self.refresh_view(self.selected_id());
+    }
+
+    pub fn search(&self) -> &str {
+        &self.search
+    }
+
+    /// Only show the items whose title or description contains `query`, ignoring case.
+    /// When the selected item does not match, the first match is selected.
+    pub fn set_search(&mut self, query: &str) {
+        self.search = query.to_string();
+        self.refresh_view(self.selected_id());
+        if !self.search.is_empty() && !self.get_selected().is_some_and(|item| self.is_match(item)) {
+            self.state.select(None);
+            self.select_next_match();
+        }
+    }
+
+    fn is_match(&self, item: &TodoItem) -> bool {
+        !self.search.is_empty()
+            && (!match_ranges(&item.title, &self.search).is_empty()
+                || !match_ranges(&item.description, &self.search).is_empty())
+    }
+
+    /// Rows of the view that match the search.
+    fn match_rows(&self) -> Vec<usize> {
+        (0..self.view.len())
+            .filter(|&position| self.is_match(&self.items[self.view[position].index]))
+            .collect()
+    }
+
+    pub fn match_count(&self) -> usize {
+        self.match_rows().len()
+    }
+
+    /// Select the next match below the selection, wrapping around to the first one.
+    pub fn select_next_match(&mut self) {
+        let rows = self.match_rows();
+        let next = match self.state.selected() {
+            Some(selected) => rows.iter().find(|&&row| row > selected).or(rows.first()),
+            None => rows.first(),
+        };
+        if let Some(&row) = next {
+            self.state.select(Some(row));
+        }
+    }
+
+    /// Select the previous match above the selection, wrapping around to the last one.
+    pub fn select_previous_match(&mut self) {
+        let rows = self.match_rows();
+        let previous = match self.state.selected() {
+            Some(selected) => rows.iter().rev().find(|&&row| row < selected).or(rows.last()),
+            None => rows.last(),
+        };
+        if let Some(&row) = previous {
+            self.state.select(Some(row));
+        }
+    }
+
+    /// Show the subtasks of the selected item.
+    pub fn expand_selected(&mut self) {
+        if let Some(id) = self.selected_id() {
+            self.collapsed.remove(&id);
+            self.refresh_view(Some(id));
+        }
+    }
+
+    /// Hide the subtasks of the selected item, or of its parent when it has none.
+    pub fn collapse_selected(&mut self) {
+        let Some(item) = self.get_selected() else {
+            return;
+        };
+        let id = match item.parent_id {
+            Some(parent) if !self.has_subtasks(item.id) => parent,
+            _ => item.id,
+        };
+        self.collapsed.insert(id);
+        self.refresh_view(Some(id));
+    }
+
+    /// Collapsed items are expanded while searching so that no match is hidden.
+    fn is_collapsed(&self, id: Uuid) -> bool {
+        self.search.is_empty() && self.collapsed.contains(&id)
+    }
+
+    fn has_subtasks(&self, id: Uuid) -> bool {
+        self.items.iter().any(|item| item.parent_id == Some(id))
+    }
+
+    /// Ids of the subtasks of `id`, their subtasks and so on.
+    pub fn descendants(&self, id: Uuid) -> Vec<Uuid> {
+        let mut found = Vec::new();
+        let mut pending = vec![id];
+        while let Some(parent) = pending.pop() {
+            for item in &self.items {
+                if item.parent_id == Some(parent) && item.id != id && !found.contains(&item.id) {
+                    found.push(item.id);
+                    pending.push(item.id);
+                }
+            }
+        }
+        found
+    }
+
+    /// The ids that exist here together with all of their subtasks, each once.
+    fn with_descendants(&self, ids: &[Uuid]) -> Vec<Uuid> {
+        let mut found: Vec<Uuid> = Vec::new();
+        for &id in ids {
+            if found.contains(&id) || !self.items.iter().any(|item| item.id == id) {
+                continue;
+            }
+            found.push(id);
+            found.extend(self.descendants(id));
+        }
+        found.sort();
+        found.dedup();
+        found
+    }
+
+    /// Ids of the parent of `id`, its parent and so on.
+    fn ancestors(&self, id: Uuid) -> Vec<Uuid> {
+        let mut found = Vec::new();
+        let mut current = id;
+        while let Some(parent) = self.items.iter().find(|item| item.id == current).and_then(|item| item.parent_id) {
+            if parent == id || found.contains(&parent) {
+                break;
+            }
+            found.push(parent);
+            current = parent;
         }
+        found
+    }
+
+    /// Completed and total subtasks below every item that has any.
+    fn count_progress(&self) -> HashMap<Uuid, (usize, usize)> {
+        let parents: HashMap<Uuid, Uuid> = self
+            .items
+            .iter()
+            .filter_map(|item| Some((item.id, item.parent_id?)))
+            .collect();
+        let mut progress: HashMap<Uuid, (usize, usize)> = HashMap::new();
+        for item in &self.items {
+            // Same walk as `ancestors`, stopping at a parent cycle
+            let mut seen = Vec::new();
+            let mut current = item.id;
+            while let Some(&parent) = parents.get(&current) {
+                if parent == item.id || seen.contains(&parent) {
+                    break;
+                }
+                seen.push(parent);
+                let (done, total) = progress.entry(parent).or_default();
+                *done += usize::from(item.is_completed());
+                *total += 1;
+                current = parent;
+            }
+        }
+        progress
+    }
+
+    /// Dependencies of `item` that are not completed yet. Removed dependencies no longer count.
+    pub fn open_dependencies<'a>(&'a self, item: &'a TodoItem) -> impl Iterator<Item = &'a TodoItem> {
+        self.items
+            .iter()
+            .filter(|dependency| item.depends_on.contains(&dependency.id) && !dependency.is_completed())
+    }
+
+    pub fn is_blocked(&self, item: &TodoItem) -> bool {
+        self.open_dependencies(item).next().is_some()
+    }
+
+    /// Items that depend on `id`.
+    pub fn dependents(&self, id: Uuid) -> impl Iterator<Item = &TodoItem> {
+        self.items.iter().filter(move |item| item.depends_on.contains(&id))
+    }
+
+    /// Whether `from` depends on `target`, directly or through other dependencies.
+    fn depends_on(&self, from: Uuid, target: Uuid) -> bool {
+        let mut seen = HashSet::new();
+        let mut pending = vec![from];
+        while let Some(id) = pending.pop() {
+            if !seen.insert(id) {
+                continue;
+            }
+            let Some(item) = self.items.iter().find(|item| item.id == id) else {
+                continue;
+            };
+            if item.depends_on.contains(&target) {
+                return true;
+            }
+            pending.extend(item.depends_on.iter().copied());
+        }
+        false
+    }
+
+    /// Resolve id prefixes as typed by the user, see [`TodoList::find_by_id_prefix`].
+    pub fn resolve_ids(&self, prefixes: &[String]) -> Result<BTreeSet<Uuid>, IdLookupError> {
+        prefixes
+            .iter()
+            .map(|prefix| self.find_by_id_prefix(prefix).map(|item| item.id))
+            .collect()
+    }
+
+    fn selected_id(&self) -> Option<Uuid> {
+        self.get_selected().map(|item| item.id)
+    }
+
+    /// Select the item with this id, expanding the items above it when they hide it.
+    pub fn select_id(&mut self, id: Uuid) {
+        for ancestor in self.ancestors(id) {
+            self.collapsed.remove(&ancestor);
+        }
+        self.refresh_view(Some(id));
+    }
+
+    pub fn get_selected(&self) -> Option<&TodoItem> {
+        let row = self.view.get(self.state.selected()?)?;
+        self.items.get(row.index)
+    }
+
+    /// Replace the item with the same id, keeping its position in the list.
+    /// The timestamps are maintained here, whatever the caller set them to.
+    ///
+    /// Completing an item completes all of its subtasks, and reopening a subtask
+    /// reopens the completed items above it. Completing is refused while a dependency
+    /// is open, and so are dependencies that would make an item depend on itself.
+    pub fn update_item(&mut self, updated: TodoItem) -> Result<(), ChangeError> {
+        let selected = self.selected_id();
+        let mut batch = StoreBatch::default();
+        self.stage_update(updated, &mut batch)?;
+        if batch.update.is_empty() {
+            return Ok(());
+        }
+        self.refresh_view(selected);
+        Ok(self.persist(|store| store.write_batch(&batch))?)
+    }
+
+    /// Make the change of [`TodoList::update_item`] in memory and add what has to be
+    /// written to `batch`. Nothing is changed when the update is refused.
+    fn stage_update(&mut self, updated: TodoItem, batch: &mut StoreBatch) -> Result<(), ChangeError> {
+        let Some(previous) = self.items.iter().find(|item| item.id == updated.id) else {
+            return Ok(());
+        };
+        if let Some(parent) = updated.parent_id
+            && previous.parent_id != Some(parent)
+            && (parent == updated.id || self.descendants(updated.id).contains(&parent))
+        {
+            return Err(ChangeError::ParentCycle(updated.title));
+        }
+        for &dependency in updated.depends_on.difference(&previous.depends_on) {
+            if dependency == updated.id || self.depends_on(dependency, updated.id) {
+                return Err(ChangeError::DependencyCycle(updated.title));
+            }
+        }
+        let related = match (previous.is_completed(), updated.is_completed()) {
+            (false, true) => self.descendants(updated.id),
+            (true, false) => self.ancestors(updated.id),
+            _ => Vec::new(),
+        };
+        let mut changes = vec![updated];
+        for item in self.items.iter().filter(|item| related.contains(&item.id)) {
+            if item.is_completed() != changes[0].is_completed() {
+                let mut item = item.clone();
+                item.toggle_completed();
+                changes.push(item);
+            }
+        }
+
+        let completing: HashSet<Uuid> = changes
+            .iter()
+            .filter(|change| change.is_completed())
+            .map(|change| change.id)
+            .collect();
+        for change in changes.iter().filter(|change| completing.contains(&change.id)) {
+            let blockers: Vec<String> = self
+                .open_dependencies(change)
+                .filter(|dependency| !completing.contains(&dependency.id))
+                .map(|dependency| dependency.title.clone())
+                .collect();
+            if !blockers.is_empty() && !self.items.iter().any(|item| item.id == change.id && item.is_completed()) {
+                return Err(ChangeError::Blocked(change.title.clone(), blockers));
+            }
+        }
+
+        // A completed occurrence of a repeating item hands its rule on to the next one
+        let today = dates::today();
+        let mut next_occurrences = Vec::new();
+        for change in changes.iter_mut() {
+            let was_completed = self.items.iter().any(|item| item.id == change.id && item.is_completed());
+            if change.is_completed()
+                && !was_completed
+                && let Some(next) = change.next_occurrence(today)
+            {
+                change.recurrence = None;
+                next_occurrences.push(next);
+            }
+        }
+
+        let now = Utc::now();
+        for change in changes.iter_mut() {
+            if let Some(item) = self.items.iter_mut().find(|item| item.id == change.id) {
+                change.touch(item, now);
+                self.changes.push(ItemChange {
+                    before: Some(item.clone()),
+                    after: Some(change.clone()),
+                });
+                *item = change.clone();
+            }
+        }
+        // Written together with the completed occurrence, so a failed write cannot lose the rule
+        for next in next_occurrences {
+            let next = self.push_new(next);
+            batch.insert.push(next);
+        }
+        batch.update.extend(changes);
+        Ok(())
+    }
+
+    pub fn get_list_to_display(&self) -> Vec<Line<'static>> {
+        let now = dates::now();
+        let progress = &self.progress;
+        self.view.iter().map(|row| {
+            let item = &self.items[row.index];
+            let status = if item.is_completed() { "[✓]" } else { "[✗]" };
+            let branch = match progress.contains_key(&item.id) {
+                true if self.is_collapsed(item.id) => "▸ ",
+                true => "▾ ",
+                false => "",
+            };
+            let mut line = Line::from(format!("{}{}", "  ".repeat(row.depth), branch));
+            if self.marked.contains(&item.id) {
+                line.push_span(Span::styled("◆ ", Style::new().fg(Color::LightMagenta)));
+            }
+            line.push_span(Span::raw(format!("{} ", status)));
+            if !item.priority.is_none() {
+                line.push_span(Span::styled(format!("[{}] ", item.priority.to_str()), item.priority.style()));
+            }
+            if self.search.is_empty() {
+                line.push_span(Span::raw(format!("{} ", item.title)));
+            } else {
+                // Items that are only shown because a subtask matches are dimmed
+                let style = match self.is_match(item) {
+                    true => Style::new(),
+                    false => Style::new().fg(Color::DarkGray),
+                };
+                line.extend(highlight(&item.title, &self.search, style));
+                line.push_span(Span::raw(" "));
+            }
+            if item.recurrence.is_some() {
+                line.push_span(Span::styled("↻ ", Style::new().fg(Color::Cyan)));
+            }
+            if let Some((done, total)) = progress.get(&item.id) {
+                line.push_span(Span::styled(format!("({}/{}) ", done, total), Style::new().fg(Color::Gray)));
+            }
+            for chip in tag_chips(&item.tags) {
+                line.push_span(chip);
+                line.push_span(Span::raw(" "));
+            }
+            if !item.is_completed() && self.is_blocked(item) {
+                line.push_span(Span::styled("blocked ", Style::new().fg(Color::LightRed)));
+            }
+            line.push_span(Span::raw("- "));
+            let color = workflow().color(&item.status);
+            line.push_span(Span::styled(item.status.to_str().to_string(), Style::new().fg(color)));
+            if let Some(due) = item.due {
+                let style = if item.is_overdue(now) {
+                    Style::new().fg(Color::LightRed)
+                } else {
+                    Style::new().fg(Color::Gray)
+                };
+                let due = dates::format_due_short(due, item.due_time);
+                line.push_span(Span::styled(format!(" ({})", due), style));
+            }
+            line
+        }).collect()
+    }
+
+    pub fn get_selected_item_display(&self) -> Text<'static> {
+        if let Some(item) = self.get_selected() {
+            let due = match item.due {
+                Some(due) => dates::format_due(due, item.due_time),
+                None => "-".to_string(),
+            };
+            let completed_at = match item.completed_at {
+                Some(completed_at) => dates::format_timestamp(completed_at),
+                None => "-".to_string(),
+            };
+            let parent = item.parent_id.and_then(|id| self.items.iter().find(|parent| parent.id == id));
+            let mut text = Text::from(format!("title: {}, id: {} \n subtask of: \n {} \n description: \n {} \n completed: \n {} \n status: \n {} \n priority: \n {} \n tags: ",
+                item.title,
+                item.id,
+                parent.map_or("-", |parent| parent.title.as_str()),
+                item.description,
+                item.is_completed(),
+                item.status.to_str(),
+                item.priority.to_str(),
+            ));
+            let mut tags = Line::from(" ");
+            for chip in tag_chips(&item.tags) {
+                tags.push_span(chip);
+                tags.push_span(" ");
+            }
+            text.push_line(tags);
+            let titles = |items: Vec<&TodoItem>| match items.is_empty() {
+                true => "-".to_string(),
+                false => items.iter().map(|item| item.title.as_str()).collect::<Vec<_>>().join(", "),
+            };
+            let depends_on = titles(self.items.iter().filter(|other| item.depends_on.contains(&other.id)).collect());
+            let waiting = titles(self.open_dependencies(item).collect());
+            let blocks = titles(self.dependents(item.id).collect());
+            text.extend(Text::from(format!(" depends on: \n {} \n waiting for: \n {} \n blocks: \n {} \n due: \n {} \n repeats: \n {} \n created: {} | updated: {} | completed: {} ",
+                depends_on,
+                waiting,
+                blocks,
+                due,
+                item.recurrence.as_deref().unwrap_or("-"),
+                dates::format_timestamp(item.created_at),
+                dates::format_timestamp(item.updated_at),
+                completed_at
+            )));
+            text
+        } else {
+            Text::from("No item selected")
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ViewRow {
+    /// Index into `TodoList::items`
+    index: usize,
+    /// How many parents are above the item
+    depth: usize,
+}
+
+/// Byte ranges of the non-overlapping occurrences of `query` in `text`, ignoring case.
+fn match_ranges(text: &str, query: &str) -> Vec<Range<usize>> {
+    fn fold(c: char) -> char {
+        c.to_lowercase().next().unwrap_or(c)
+    }
+    let query: Vec<char> = query.chars().map(fold).collect();
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    while !query.is_empty() && start + query.len() <= chars.len() {
+        let candidate = &chars[start..start + query.len()];
+        if candidate.iter().zip(&query).all(|(&(_, c), &q)| fold(c) == q) {
+            let end = chars.get(start + query.len()).map_or(text.len(), |&(position, _)| position);
+            ranges.push(chars[start].0..end);
+            start += query.len();
+        } else {
+            start += 1;
+        }
+    }
+    ranges
+}
+
+/// `text` in `style` with the occurrences of `query` highlighted.
+fn highlight(text: &str, query: &str, style: Style) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut last = 0;
+    for range in match_ranges(text, query) {
+        spans.push(Span::styled(text[last..range.start].to_string(), style));
+        spans.push(Span::styled(text[range.clone()].to_string(), Style::new().fg(Color::Black).bg(Color::Yellow)));
+        last = range.end;
+    }
+    spans.push(Span::styled(text[last..].to_string(), style));
+    spans
+}
+
+fn tag_chips(tags: &BTreeSet<String>) -> Vec<Span<'static>> {
+    let style = Style::new().fg(Color::Black).bg(Color::Cyan);
+    tags.iter().map(|tag| Span::styled(format!(" {} ", tag), style)).collect()
+}
+
+/// How a bulk action went.
+#[derive(Debug, Default)]
+pub struct BulkSummary {
+    /// How many items were changed
+    pub changed: usize,
+    /// Why the other items were not changed
+    pub skipped: Vec<String>,
+}
+
+/// Why a change to an item was not made.
+#[derive(Debug)]
+pub enum ChangeError {
+    /// The item, and the open items it depends on
+    Blocked(String, Vec<String>),
+    /// The item would end up depending on itself
+    DependencyCycle(String),
+    /// The item would end up a subtask of itself
+    ParentCycle(String),
+    Storage(StorageError),
+}
+
+impl fmt::Display for ChangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChangeError::Blocked(title, blockers) => {
+                write!(f, "{} cannot be completed, it is waiting for {}", title, blockers.join(", "))
+            }
+            ChangeError::DependencyCycle(title) => write!(f, "{} would end up depending on itself", title),
+            ChangeError::ParentCycle(title) => {
+                write!(f, "{} cannot be a subtask of itself or of one of its subtasks", title)
+            }
+            ChangeError::Storage(err) => write!(f, "Save failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ChangeError {}
+
+impl From<StorageError> for ChangeError {
+    fn from(err: StorageError) -> Self {
+        ChangeError::Storage(err)
+    }
+}
+
+#[derive(Debug)]
+pub enum IdLookupError {
+    NotFound(String),
+    Ambiguous(String, usize),
+}
+
+impl fmt::Display for IdLookupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IdLookupError::NotFound(prefix) => write!(f, "No todo with an id starting with {}", prefix),
+            IdLookupError::Ambiguous(prefix, count) => {
+                write!(f, "{} todos have an id starting with {}, use a longer prefix", count, prefix)
+            }
+        }
+    }
+}
+
+impl std::error::Error for IdLookupError {}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, fs, io, rc::Rc};
+
+    use super::*;
+    use crate::storage::{Backups, JsonStore};
+
+    /// What a [`MemoryStore`] holds, shared with the test.
+    #[derive(Debug, Default)]
+    struct Stored {
+        items: Vec<TodoItem>,
+        writes: usize,
+        fail: bool,
+    }
+
+    /// Keeps the items in memory, counts the writes and fails them on request.
+    #[derive(Debug)]
+    struct MemoryStore(Rc<RefCell<Stored>>);
+
+    impl MemoryStore {
+        fn write(&self, write: impl FnOnce(&mut Vec<TodoItem>)) -> Result<(), StorageError> {
+            let mut stored = self.0.borrow_mut();
+            if stored.fail {
+                return Err(StorageError::io("memory", io::Error::other("disk full")));
+            }
+            stored.writes += 1;
+            write(&mut stored.items);
+            Ok(())
+        }
+    }
+
+    impl TodoStore for MemoryStore {
+        fn load(&mut self) -> Result<Vec<TodoItem>, StorageError> {
+            Ok(self.0.borrow().items.clone())
+        }
+
+        fn write_batch(&mut self, batch: &StoreBatch) -> Result<(), StorageError> {
+            self.write(|items| {
+                items.retain(|item| !batch.delete.contains(&item.id));
+                for item in &batch.update {
+                    if let Some(stored) = items.iter_mut().find(|stored| stored.id == item.id) {
+                        *stored = item.clone();
+                    }
+                }
+                items.extend(batch.insert.iter().cloned());
+            })
+        }
+
+        fn query(&mut self, query: &TodoQuery) -> Result<Vec<TodoItem>, StorageError> {
+            Ok(self.0.borrow().items.iter().filter(|item| query.matches(item)).cloned().collect())
+        }
+
+        fn save_all(&mut self, items: &[TodoItem]) -> Result<(), StorageError> {
+            self.write(|stored| *stored = items.to_vec())
+        }
+
+        fn list_backups(&self) -> Result<Vec<PathBuf>, StorageError> {
+            Ok(Vec::new())
+        }
+
+        fn restore_backup(&mut self, name: &str) -> Result<PathBuf, StorageError> {
+            Err(StorageError::BackupNotFound(name.to_string()))
+        }
+
+        fn recover_from_backup(&mut self) -> Result<(), StorageError> {
+            Ok(())
+        }
+
+        fn start_empty(&mut self) -> Result<(), StorageError> {
+            Ok(())
+        }
+    }
+
+    /// An empty list kept in memory, together with what its store holds.
+    fn memory_list() -> (TodoList, Rc<RefCell<Stored>>) {
+        let stored = Rc::new(RefCell::new(Stored::default()));
+        let mut todos = TodoList::new("memory", Box::new(MemoryStore(stored.clone())));
+        todos.read_todos().unwrap();
+        (todos, stored)
+    }
+
+    fn dir(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("todo-list-{}-{}", name, std::process::id()))
+    }
+
+    /// An empty list backed by a JSON file in a fresh temporary directory.
+    fn list(name: &str) -> TodoList {
+        let _ = fs::remove_dir_all(dir(name));
+        fs::create_dir_all(dir(name)).unwrap();
+        open(name)
+    }
+
+    /// The list as it was saved by [`list`].
+    fn open(name: &str) -> TodoList {
+        let path = dir(name).join("data.json");
+        let store = JsonStore::new(path.clone(), Backups::new(&path, 0));
+        let mut todos = TodoList::new(name, Box::new(store));
+        todos.read_todos().unwrap();
+        todos
+    }
+
+    fn add(todos: &mut TodoList, title: &str, depends_on: &[Uuid]) -> Uuid {
+        let mut item = TodoItem::new_todo_item(title.to_string(), String::new(), TodoStatus::default());
+        item.depends_on = depends_on.iter().copied().collect();
+        let id = item.id;
+        todos.add_item(item).unwrap();
+        id
+    }
+
+    fn add_subtask(todos: &mut TodoList, title: &str, parent: Uuid) -> Uuid {
+        let mut item = TodoItem::new_todo_item(title.to_string(), String::new(), TodoStatus::default());
+        item.parent_id = Some(parent);
+        let id = item.id;
+        todos.add_item(item).unwrap();
+        id
+    }
+
+    fn get(todos: &TodoList, id: Uuid) -> &TodoItem {
+        todos.items.iter().find(|item| item.id == id).unwrap()
+    }
+
+    #[test]
+    fn moving_drops_dependencies_in_both_directions() {
+        let mut source = list("move-source");
+        let mut target = list("move-target");
+        let stays = add(&mut source, "stays", &[]);
+        let moved = add(&mut source, "moved", &[stays]);
+        let waiting = add(&mut source, "waiting", &[moved]);
+        let subtask = add_subtask(&mut source, "subtask", moved);
+
+        assert_eq!(source.move_item(moved, &mut target).unwrap(), 2);
+
+        let saved = open("move-target");
+        assert_eq!(get(&saved, subtask).parent_id, Some(moved));
+        assert_eq!(open("move-source").items.len(), 2);
+        assert!(get(&target, moved).depends_on.is_empty());
+        assert!(get(&source, waiting).depends_on.is_empty());
+        assert!(!source.is_blocked(get(&source, waiting)));
+    }
+
+    #[test]
+    fn removing_a_todo_saves_what_is_left() {
+        let mut todos = list("remove");
+        let parent = add(&mut todos, "parent", &[]);
+        let child = add_subtask(&mut todos, "child", parent);
+        let grandchild = add_subtask(&mut todos, "grandchild", child);
+        let waiting = add(&mut todos, "waiting", &[grandchild]);
+
+        todos.remove_item(parent).unwrap();
+
+        let saved = open("remove");
+        assert_eq!(saved.items.len(), 1);
+        assert!(get(&saved, waiting).depends_on.is_empty());
+    }
+
+    #[test]
+    fn removing_several_todos_counts_each_once() {
+        let mut todos = list("remove-many");
+        let parent = add(&mut todos, "parent", &[]);
+        let child = add_subtask(&mut todos, "child", parent);
+        let other = add(&mut todos, "other", &[]);
+        let waiting = add(&mut todos, "waiting", &[child, other]);
+
+        let summary = todos.remove_many(&[child, parent, other]).unwrap();
+
+        assert_eq!(summary.changed, 3);
+        let saved = open("remove-many");
+        assert_eq!(saved.items.len(), 1);
+        assert!(get(&saved, waiting).depends_on.is_empty());
+    }
+
+    #[test]
+    fn applying_the_reversed_changes_restores_the_saved_todos() {
+        let mut todos = list("apply");
+        let parent = add(&mut todos, "parent", &[]);
+        add_subtask(&mut todos, "child", parent);
+        add(&mut todos, "waiting", &[parent]);
+        let before = open("apply").items;
+        todos.take_changes();
+
+        todos.remove_item(parent).unwrap();
+        let undo: Vec<ItemChange> = todos
+            .take_changes()
+            .into_iter()
+            .rev()
+            .map(|change| ItemChange {
+                before: change.after,
+                after: change.before,
+            })
+            .collect();
+        todos.apply(&undo).unwrap();
+
+        let mut saved = open("apply").items;
+        saved.sort_by_key(|item| item.id);
+        let mut expected = before;
+        expected.sort_by_key(|item| item.id);
+        assert_eq!(saved, expected);
+    }
+
+    #[test]
+    fn changes_made_since_are_noticed() {
+        let (mut todos, _) = memory_list();
+        let id = add(&mut todos, "a", &[]);
+        let other = add(&mut todos, "b", &[]);
+        let undo: Vec<ItemChange> = todos
+            .take_changes()
+            .into_iter()
+            .map(|change| ItemChange {
+                before: change.after,
+                after: change.before,
+            })
+            .collect();
+        assert!(todos.is_unchanged_since(&undo));
+
+        let mut item = get(&todos, id).clone();
+        item.title = "renamed".to_string();
+        todos.update_item(item).unwrap();
+        assert!(!todos.is_unchanged_since(&undo[..1]));
+        assert!(todos.is_unchanged_since(&undo[1..]));
+        todos.remove_item(other).unwrap();
+        assert!(!todos.is_unchanged_since(&undo[1..]));
+    }
+
+    #[test]
+    fn subtask_progress_follows_changes() {
+        let (mut todos, _) = memory_list();
+        let parent = add(&mut todos, "parent", &[]);
+        let child = add_subtask(&mut todos, "child", parent);
+        add_subtask(&mut todos, "grandchild", child);
+        assert_eq!(todos.progress[&parent], (0, 2));
+
+        let mut completed = get(&todos, child).clone();
+        completed.toggle_completed();
+        todos.update_item(completed).unwrap();
+        assert_eq!(todos.progress[&parent], (2, 2));
+        assert_eq!(todos.progress[&child], (1, 1));
+    }
+
+    #[test]
+    fn a_todo_cannot_become_a_subtask_of_its_subtask() {
+        let mut todos = list("parent-cycle");
+        let parent = add(&mut todos, "parent", &[]);
+        let child = add_subtask(&mut todos, "child", parent);
+
+        let mut updated = get(&todos, parent).clone();
+        updated.parent_id = Some(child);
+
+        assert!(matches!(todos.update_item(updated), Err(ChangeError::ParentCycle(_))));
+        assert_eq!(get(&todos, parent).parent_id, None);
+    }
+
+    #[test]
+    fn completing_a_todo_writes_its_subtasks_in_the_same_batch() {
+        let (mut todos, stored) = memory_list();
+        let parent = add(&mut todos, "parent", &[]);
+        let child = add_subtask(&mut todos, "child", parent);
+        add_subtask(&mut todos, "grandchild", child);
+        stored.borrow_mut().writes = 0;
+
+        let mut completed = get(&todos, parent).clone();
+        completed.toggle_completed();
+        todos.update_item(completed).unwrap();
+
+        let stored = stored.borrow();
+        assert_eq!(stored.writes, 1);
+        assert!(stored.items.iter().all(TodoItem::is_completed));
+    }
+
+    #[test]
+    fn a_failed_write_keeps_the_next_occurrence() {
+        let (mut todos, stored) = memory_list();
+        let mut item = TodoItem::new_todo_item("water plants".to_string(), String::new(), TodoStatus::default());
+        item.recurrence = Some("daily".to_string());
+        item.set_due(Some(dates::Due::new(dates::today(), None)));
+        let id = item.id;
+        todos.add_item(item).unwrap();
+        stored.borrow_mut().fail = true;
+
+        let mut completed = get(&todos, id).clone();
+        completed.toggle_completed();
+        assert!(matches!(todos.update_item(completed), Err(ChangeError::Storage(_))));
+        stored.borrow_mut().fail = false;
+        add(&mut todos, "other", &[]);
+
+        let stored = stored.borrow();
+        assert_eq!(stored.items.len(), 3);
+        let next = stored.items.iter().find(|item| item.id != id && item.title == "water plants").unwrap();
+        assert_eq!(next.recurrence.as_deref(), Some("daily"));
+        assert!(!next.is_completed());
+    }
+
+    #[test]
+    fn bulk_changes_are_written_once() {
+        let (mut todos, stored) = memory_list();
+        let first = add(&mut todos, "first", &[]);
+        let second = add(&mut todos, "second", &[]);
+        let blocked = add(&mut todos, "blocked", &[first]);
+        stored.borrow_mut().writes = 0;
+
+        let summary = todos.complete_many(&[blocked, first, second], false).unwrap();
+
+        assert_eq!(summary.changed, 2);
+        assert_eq!(summary.skipped.len(), 1);
+        assert_eq!(stored.borrow().writes, 1);
+        assert!(!get(&todos, blocked).is_completed());
+    }
+
+    #[test]
+    fn a_failed_bulk_write_still_clears_the_marks() {
+        let (mut todos, stored) = memory_list();
+        let first = add(&mut todos, "first", &[]);
+        let second = add(&mut todos, "second", &[]);
+        todos.marked.extend([first, second]);
+        stored.borrow_mut().fail = true;
+
+        assert!(todos.complete_many(&[first, second], false).is_err());
+
+        assert!(todos.marked.is_empty());
+        assert!(todos.items.iter().all(TodoItem::is_completed));
     }
 }
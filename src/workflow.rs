@@ -0,0 +1,177 @@
+use std::{str::FromStr, sync::OnceLock};
+
+use ratatui::style::Color;
+
+use crate::{config::StatusConfig, todo::TodoStatus};
+
+static WORKFLOW: OnceLock<Workflow> = OnceLock::new();
+
+/// Make `workflow` the one used for the rest of the run. Only the first call has an effect.
+pub fn install(workflow: Workflow) {
+    let _ = WORKFLOW.set(workflow);
+}
+
+/// The installed workflow, or the built-in Pending -> InProgress -> Completed one.
+pub fn workflow() -> &'static Workflow {
+    WORKFLOW.get_or_init(Workflow::default)
+}
+
+#[derive(Debug, Clone)]
+struct WorkflowStatus {
+    status: TodoStatus,
+    color: Color,
+    done: bool,
+}
+
+/// The ordered statuses a todo moves through and which of them count as done.
+///
+/// Stored statuses that are not part of the workflow (for example after a status was
+/// removed from the config) are kept as they are; they count as open and are shown in gray.
+#[derive(Debug, Clone)]
+pub struct Workflow {
+    statuses: Vec<WorkflowStatus>,
+}
+
+impl Default for Workflow {
+    fn default() -> Self {
+        let status = |name: &str, color, done| WorkflowStatus {
+            status: TodoStatus::new(name),
+            color,
+            done,
+        };
+        Self {
+            statuses: vec![
+                status("Pending", Color::Reset, false),
+                status("InProgress", Color::Yellow, false),
+                status("Completed", Color::Green, true),
+            ],
+        }
+    }
+}
+
+impl Workflow {
+    /// Build the workflow from the `[[statuses]]` of the config, the built-in one when there are none.
+    pub fn from_config(statuses: &[StatusConfig]) -> Result<Workflow, String> {
+        if statuses.is_empty() {
+            return Ok(Workflow::default());
+        }
+
+        let mut workflow = Workflow { statuses: Vec::new() };
+        for config in statuses {
+            let name = config.name.trim();
+            if name.is_empty() {
+                return Err("a status needs a name".to_string());
+            }
+            if workflow.find(name).is_some() {
+                return Err(format!("status {} is defined twice", name));
+            }
+            let color = match &config.color {
+                Some(color) => Color::from_str(color)
+                    .map_err(|_| format!("unknown color {} for status {}", color, name))?,
+                None => Color::Reset,
+            };
+            workflow.statuses.push(WorkflowStatus {
+                status: TodoStatus::new(name),
+                color,
+                done: config.done,
+            });
+        }
+
+        if !workflow.statuses.iter().any(|status| status.done) {
+            return Err("at least one status needs `done = true`".to_string());
+        }
+        if workflow.statuses.iter().all(|status| status.done) {
+            return Err("at least one status needs to be open".to_string());
+        }
+        Ok(workflow)
+    }
+
+    /// All statuses, in workflow order.
+    pub fn statuses(&self) -> Vec<TodoStatus> {
+        self.statuses.iter().map(|status| status.status.clone()).collect()
+    }
+
+    /// The status with this name, ignoring case like every comparison of statuses.
+    pub fn find(&self, name: &str) -> Option<&TodoStatus> {
+        self.statuses
+            .iter()
+            .map(|status| &status.status)
+            .find(|status| status.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Where new and reopened todos start: the first open status.
+    pub fn initial(&self) -> &TodoStatus {
+        self.statuses
+            .iter()
+            .find(|status| !status.done)
+            .map(|status| &status.status)
+            .expect("a workflow has an open status")
+    }
+
+    /// What completing a todo sets: the first done status.
+    pub fn done_status(&self) -> &TodoStatus {
+        self.statuses
+            .iter()
+            .find(|status| status.done)
+            .map(|status| &status.status)
+            .expect("a workflow has a done status")
+    }
+
+    pub fn is_done(&self, status: &TodoStatus) -> bool {
+        self.get(status).is_some_and(|status| status.done)
+    }
+
+    pub fn color(&self, status: &TodoStatus) -> Color {
+        self.get(status).map_or(Color::Gray, |status| status.color)
+    }
+
+    /// The status after `status`, the last one stays where it is.
+    pub fn next(&self, status: &TodoStatus) -> TodoStatus {
+        match self.position(status) {
+            Some(position) => self.statuses[(position + 1).min(self.statuses.len() - 1)].status.clone(),
+            None => self.initial().clone(),
+        }
+    }
+
+    /// The status before `status`, the first one stays where it is.
+    pub fn previous(&self, status: &TodoStatus) -> TodoStatus {
+        match self.position(status) {
+            Some(position) => self.statuses[position.saturating_sub(1)].status.clone(),
+            None => self.initial().clone(),
+        }
+    }
+
+    fn position(&self, status: &TodoStatus) -> Option<usize> {
+        self.statuses.iter().position(|known| known.status == *status)
+    }
+
+    fn get(&self, status: &TodoStatus) -> Option<&WorkflowStatus> {
+        self.statuses.iter().find(|known| known.status == *status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statuses_are_looked_up_ignoring_case() {
+        let statuses = [("Todo", false), ("Done", true)].map(|(name, done)| StatusConfig {
+            name: name.to_string(),
+            color: None,
+            done,
+        });
+        let workflow = Workflow::from_config(&statuses).unwrap();
+        assert!(workflow.is_done(&TodoStatus::new("done")));
+        assert_eq!(workflow.next(&TodoStatus::new("TODO")).name(), "Done");
+        assert_eq!(workflow.find("todo").map(TodoStatus::name), Some("Todo"));
+    }
+
+    #[test]
+    fn a_known_status_is_read_in_the_configured_spelling() {
+        let known: TodoStatus = serde_json::from_str("\"inprogress\"").unwrap();
+        assert_eq!(known.name(), "InProgress");
+        let unknown: TodoStatus = serde_json::from_str("\"someday\"").unwrap();
+        assert_eq!(unknown.name(), "someday");
+    }
+}